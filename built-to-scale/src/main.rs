@@ -7,24 +7,26 @@
 
 use agb::{
     display::{
+        blend::{Blend, BlendMode, Layer},
         tiled::{
-            InfiniteScrolledMap, PartialUpdateStatus, RegularBackgroundSize, TileFormat, TiledMap,
+            BackgroundID, InfiniteScrolledMap, PartialUpdateStatus, RegularBackgroundSize,
+            TileFormat, Tiled0, TiledMap, VRamManager,
         },
         Priority, HEIGHT, WIDTH,
     },
-    fixnum::Vector2D,
-    input::ButtonController,
+    fixnum::{Num, Vector2D},
     interrupt::VBlank,
     sound::mixer::{Frequency, SoundChannel},
 };
 use agb_tracker::Tracker;
-use alloc::{boxed::Box, vec::Vec};
-use scenes::{Display, SceneManager, Update};
+use alloc::{boxed::Box, vec, vec::Vec};
+use scenes::{Display, Fade, SceneManager, Update};
+use util::{CameraTransform, InputSnapshot, InputSource, LiveInput, Number, RealSpace};
 
 extern crate alloc;
 
-mod mission_logs;
 mod resources;
+mod save;
 mod scenes;
 
 #[agb::entry]
@@ -33,45 +35,24 @@ fn main(gba: agb::Gba) -> ! {
 }
 
 fn entry(mut gba: agb::Gba) -> ! {
-    let mut scene = SceneManager::new();
+    let level = map::Level::by_name("main").expect("Should have a main level");
+    let mut saves = save::SaveSlots::new(&mut gba.save);
+    let mut scene = SceneManager::new(
+        level,
+        saves.load_all(),
+        saves.load_replay(),
+        saves.load_ghost(),
+    );
 
     let (mut unmanaged, mut loader) = gba.display.object.get_unmanaged();
     let (tiles, mut vram) = gba.display.video.tiled0();
 
     vram.set_background_palettes(resources::bg::PALETTES);
 
-    let planet_background = tiles.background(
-        Priority::P0,
-        RegularBackgroundSize::Background32x32,
-        TileFormat::EightBpp,
-    );
-
-    let mut planet_scrolled_map =
-        infinite_scroll_wrapper(planet_background, map::get_planet_tile_chunk);
-
-    planet_scrolled_map.init(
-        &mut vram,
-        map::CAMERA_START.floor() + (-WIDTH / 2, -HEIGHT / 2).into(),
-        &mut || {},
-    );
-
-    let platform_background = tiles.background(
-        Priority::P1,
-        RegularBackgroundSize::Background32x32,
-        TileFormat::EightBpp,
-    );
-
-    let mut platform_scrolled_map =
-        infinite_scroll_wrapper(platform_background, map::get_platform_tile_chunk);
-
-    platform_scrolled_map.init(
-        &mut vram,
-        map::CAMERA_START.floor() + (-WIDTH / 2, -HEIGHT / 2).into(),
-        &mut || {},
-    );
+    let mut scrolled_maps = load_level_backgrounds(level, &tiles, &mut vram);
 
     let mut star_background = tiles.background(
-        Priority::P2,
+        Priority::P3,
         RegularBackgroundSize::Background32x32,
         TileFormat::FourBpp,
     );
@@ -86,37 +67,77 @@ fn entry(mut gba: agb::Gba) -> ! {
         }
     }
 
-    planet_scrolled_map.commit(&mut vram);
+    for (layer, scrolled_map) in scrolled_maps.iter_mut() {
+        scrolled_map.commit(&mut vram);
+        scrolled_map.set_visible(layer.visible);
+    }
     star_background.commit(&mut vram);
-    platform_scrolled_map.commit(&mut vram);
-
-    planet_scrolled_map.set_visible(true);
     star_background.set_visible(true);
-    platform_scrolled_map.set_visible(true);
+
+    let mut blend = gba.display.blend.get();
+    blend.set_blend_mode(BlendMode::FadeToBlack);
 
     let vblank = VBlank::get();
 
-    let mut button_controller = ButtonController::new();
+    let mut input = LiveInput::new();
+    let mut input_snapshot = InputSnapshot::default();
 
     let mut mixer = gba.mixer.mixer(Frequency::Hz32768);
     mixer.enable();
     let mut tracker = Tracker::new(&sfx::GROUND_MUSIC);
     let mut is_playing_space_music = false;
+    let mut frame_count = 0;
+    // the tile each animation last copied in, so that it's only copied again when it changes
+    let mut shown_tiles = vec![None; map::TILE_ANIMATIONS.len()];
 
     loop {
-        button_controller.update();
+        input_snapshot = input.next_snapshot(input_snapshot);
 
         {
-            let mut update = Update::new(&button_controller, &mut mixer);
+            let mut update = Update::new(input_snapshot, &mut mixer);
 
             scene.frame(&mut update);
 
-            if let Some(new_pos) = update.new_pos() {
-                while planet_scrolled_map.set_pos(&mut vram, new_pos) != PartialUpdateStatus::Done {
+            if let Some((slot, game)) = update.saved_game() {
+                saves.save(slot, &game);
+            }
+
+            if let Some(replay) = update.take_saved_replay() {
+                let bytes = replay.encode();
+                if !saves.save_replay(&bytes) {
+                    agb::println!("Replay of {} bytes doesn't fit in save memory", bytes.len());
                 }
+                save::log_replay(&bytes);
+                scene.set_replay(replay);
+            }
+
+            // runs which don't fit aren't kept, so they aren't shown as the best either
+            if let Some(ghost) = update.saved_ghost() {
+                if saves.save_ghost(ghost) {
+                    scene.set_best_run(ghost.clone());
+                }
+            }
+
+            if let Some(level) = update.new_level() {
+                // the old level's backgrounds need freeing before the new ones can be loaded
+                for (_, scrolled_map) in scrolled_maps.iter_mut() {
+                    scrolled_map.clear(&mut vram);
+                }
+                scrolled_maps.clear();
+
+                scrolled_maps = load_level_backgrounds(level, &tiles, &mut vram);
+                // the animated tiles may have been loaded afresh, so they all need copying again
+                shown_tiles.fill(None);
+                for (layer, scrolled_map) in scrolled_maps.iter_mut() {
+                    scrolled_map.commit(&mut vram);
+                    scrolled_map.set_visible(layer.visible);
+                }
+            }
 
-                while platform_scrolled_map.set_pos(&mut vram, new_pos) != PartialUpdateStatus::Done
-                {
+            if let Some(new_pos) = update.new_pos() {
+                for (layer, scrolled_map) in scrolled_maps.iter_mut() {
+                    let layer_pos = parallax_position(layer, new_pos);
+                    while scrolled_map.set_pos(&mut vram, layer_pos) != PartialUpdateStatus::Done {}
                 }
 
                 let star_pos = new_pos / 16;
@@ -134,23 +155,142 @@ fn entry(mut gba: agb::Gba) -> ! {
             }
         }
         vblank.wait_for_vblank();
+        apply_fade(
+            &mut blend,
+            scene.fade(),
+            scrolled_maps
+                .iter()
+                .map(|(_, scrolled_map)| scrolled_map.background())
+                .chain([star_background.background()]),
+        );
         scene.display(&mut Display::new(unmanaged.iter(), &mut loader));
 
-        planet_scrolled_map.commit(&mut vram);
-        platform_scrolled_map.commit(&mut vram);
+        for (_, scrolled_map) in scrolled_maps.iter_mut() {
+            scrolled_map.commit(&mut vram);
+        }
         star_background.commit(&mut vram);
 
+        animate_tiles(&mut vram, frame_count, &mut shown_tiles);
+        frame_count = frame_count.wrapping_add(1);
+
         tracker.step(&mut mixer);
         mixer.frame();
     }
 }
 
+/// Darkens the backgrounds, and the sprites too if the fade asks for it. Translucent sprites are
+/// blended half and half with the backgrounds behind them instead.
+fn apply_fade(
+    blend: &mut Blend,
+    fade: Fade,
+    backgrounds: impl Iterator<Item = BackgroundID> + Clone,
+) {
+    blend.reset_targets();
+    blend.set_blend_mode(BlendMode::FadeToBlack);
+
+    let mut top = blend.layer(Layer::Top);
+    for background in backgrounds.clone() {
+        top.set_background_enable(background, true);
+    }
+    top.set_object_enable(fade.sprites)
+        .set_backdrop_enable(true);
+
+    let mut bottom = blend.layer(Layer::Bottom);
+    for background in backgrounds {
+        bottom.set_background_enable(background, true);
+    }
+    bottom.set_backdrop_enable(true);
+
+    // the backgrounds behind a translucent sprite are blended in before they're faded, so they
+    // have to be darkened here too
+    let background_weight = Num::from_raw((16 - fade.amount) / 2);
+    let sprite_weight = if fade.sprites {
+        background_weight
+    } else {
+        Num::from_raw(8)
+    };
+
+    blend
+        .set_blend_weight(Layer::Top, sprite_weight)
+        .set_blend_weight(Layer::Bottom, background_weight)
+        .set_fade(Num::from_raw(fade.amount))
+        .commit();
+}
+
+fn load_level_backgrounds<'a>(
+    level: &'static map::Level,
+    tiles: &'a Tiled0<'_>,
+    vram: &mut VRamManager,
+) -> Vec<(&'static map::TileLayer, InfiniteScrolledMap<'a>)> {
+    level
+        .tile_layers
+        .iter()
+        .map(|layer| {
+            let background = tiles.background(
+                priority(layer.priority),
+                RegularBackgroundSize::Background32x32,
+                TileFormat::EightBpp,
+            );
+
+            let mut scrolled_map =
+                infinite_scroll_wrapper(background, |x, y| layer.get_tile_chunk(x, y));
+
+            scrolled_map.init(
+                vram,
+                parallax_position(
+                    layer,
+                    CameraTransform::new(RealSpace(level.camera_start), (WIDTH, HEIGHT).into())
+                        .top_left(),
+                ),
+                &mut || {},
+            );
+
+            (layer, scrolled_map)
+        })
+        .collect()
+}
+
+/// Swaps the tile data of every animated tile which has moved on to a new frame
+fn animate_tiles(vram: &mut VRamManager, frame_count: u32, shown_tiles: &mut [Option<u16>]) {
+    for (animation, shown_tile) in map::TILE_ANIMATIONS.iter().zip(shown_tiles) {
+        let Some(tileset) = resources::animated_map_tileset(animation.map_tile_set) else {
+            continue;
+        };
+
+        let tile = animation.tile_at(frame_count);
+        if *shown_tile == Some(tile) {
+            continue;
+        }
+
+        vram.replace_tile(&tileset.tiles, animation.tile_id, &tileset.tiles, tile);
+        *shown_tile = Some(tile);
+    }
+}
+
+fn priority(priority: u8) -> Priority {
+    match priority {
+        0 => Priority::P0,
+        1 => Priority::P1,
+        2 => Priority::P2,
+        _ => Priority::P3,
+    }
+}
+
+/// Where a layer should be scrolled to given the top left of the camera. Parallax is
+/// relative to the centre of the screen, the same as in Tiled.
+fn parallax_position(layer: &map::TileLayer, position: Vector2D<i32>) -> Vector2D<i32> {
+    let screen_centre = Vector2D::new(WIDTH / 2, HEIGHT / 2);
+    let centre: Vector2D<Number> = (position + screen_centre).change_base();
+
+    centre.hadamard(layer.parallax).floor() - screen_centre
+}
+
 fn infinite_scroll_wrapper<'a>(
-    planet_background: agb::display::tiled::MapLoan<'a, agb::display::tiled::RegularMap>,
+    background: agb::display::tiled::MapLoan<'a, agb::display::tiled::RegularMap>,
     get_chunk_data: impl Fn(i32, i32) -> &'static [map::MapTileSetting] + 'a,
 ) -> InfiniteScrolledMap<'a> {
     InfiniteScrolledMap::new(
-        planet_background,
+        background,
         Box::new(move |pos| {
             let chunk = Vector2D::new(pos.x.div_floor(8), pos.y.div_floor(8));
             let chunk_x = pos.x.rem_euclid(8);
@@ -159,10 +299,13 @@ fn infinite_scroll_wrapper<'a>(
             let chunk_data = get_chunk_data(chunk.x, chunk.y);
             let map_tile_setting = chunk_data[(chunk_x + chunk_y * 8) as usize];
 
-            let tileset = match map_tile_setting.map_tile_set {
-                map::MapTileSet::Planets => &resources::bg::planets,
-                map::MapTileSet::Planets2 => &resources::bg::planets2,
-                map::MapTileSet::Platforms => &resources::bg::platforms,
+            // animated tiles get their tile data swapped out, so they can't share tiles with
+            // the deduplicated tileset
+            let tileset = if map_tile_setting.animated {
+                resources::animated_map_tileset(map_tile_setting.map_tile_set)
+                    .expect("Tileset with animated tiles should be included")
+            } else {
+                resources::map_tileset(map_tile_setting.map_tile_set)
             };
 
             (
@@ -1,5 +1,10 @@
 use agb::{
-    display::{object::Graphics, palette16::Palette16, Font},
+    display::{
+        object::{Graphics, Tag},
+        palette16::Palette16,
+        tile_data::TileData,
+        Font,
+    },
     include_aseprite, include_background_gfx, include_font, include_wav,
 };
 
@@ -12,14 +17,39 @@ static SPRITES: &Graphics = include_aseprite!(
 
 pub static FONT: Font = include_font!("fnt/Dungeon Puzzler Font.ttf", 8);
 
-include_background_gfx!(backgrounds, "000000",
-    dummy => deduplicate "gfx/sprites.aseprite",
-    planets => 256 deduplicate "gfx/background.aseprite",
-    planets2 => 256 deduplicate "gfx/background2.aseprite",
-    platforms => 256 deduplicate "gfx/platforms.aseprite",
+// the tilesets used by the map are found by the map compiler, so they get passed in here.
+// Tilesets with animated tiles are also included without deduplication, so the animation
+// frames can be found by their tile id in Tiled.
+macro_rules! include_backgrounds {
+    (
+        [$($variant:ident $module:ident => $path:literal),* $(,)?]
+        [$($animated_variant:ident $animated_module:ident => $animated_path:literal),* $(,)?]
+    ) => {
+        include_background_gfx!(backgrounds, "000000",
+            dummy => deduplicate "gfx/sprites.aseprite",
+            $($module => 256 deduplicate $path,)*
+            $($animated_module => 256 $animated_path,)*
 
-    stars => deduplicate "gfx/stars.aseprite",
-);
+            stars => deduplicate "gfx/stars.aseprite",
+        );
+
+        pub fn map_tileset(tileset: map::MapTileSet) -> &'static TileData {
+            match tileset {
+                $(map::MapTileSet::$variant => &backgrounds::$module,)*
+            }
+        }
+
+        pub fn animated_map_tileset(tileset: map::MapTileSet) -> Option<&'static TileData> {
+            #[allow(unreachable_patterns)]
+            match tileset {
+                $(map::MapTileSet::$animated_variant => Some(&backgrounds::$animated_module),)*
+                _ => None,
+            }
+        }
+    };
+}
+
+map::with_map_tilesets!(include_backgrounds);
 
 pub mod bg {
     use super::backgrounds;
@@ -49,9 +79,6 @@ extract_statics!(
         FALL,
         BUBBLE,
         BUBBLE_POP,
-        JUMP_BOOST,
-        DOUBLE_JUMP,
-        DASH,
         SLIME_MOON,
         PLATFORM_UP_RIGHT,
         PLATFORM_VERTICAL,
@@ -64,6 +91,11 @@ extract_statics!(
     ]
 );
 
+/// Looks up sprites which are named in the map rather than in code
+pub fn sprite_tag(name: &str) -> &'static Tag {
+    SPRITES.tags().get(name)
+}
+
 pub static JUMP_SOUND: &[u8] = include_wav!("game-sfx/jump.wav");
 pub static POWER_UP_SOUND: &[u8] = include_wav!("game-sfx/power_up.wav");
 pub static DASH_SOUND: &[u8] = include_wav!("game-sfx/dash.wav");
@@ -73,3 +105,22 @@ pub static RECOVERY_SOUND: &[u8] = include_wav!("game-sfx/recovery.wav");
 pub static TEXT_PALETTE: Palette16 = Palette16::new([
     0, 0xFFFF, 0, 0xFFFF, 0x2b9f, 0x321f, 0x2b6c, 0x7ee6, 0x3619, 0x2485, 0, 0, 0, 0, 0, 0,
 ]);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn every_power_up_has_a_sprite(_gba: &mut agb::Gba) {
+        for level in map::LEVELS {
+            for power_up in level.power_ups {
+                assert!(
+                    SPRITES.tags().try_get(power_up.sprite).is_some(),
+                    "No sprite called {} for a power up in {}",
+                    power_up.sprite,
+                    level.name
+                );
+            }
+        }
+    }
+}
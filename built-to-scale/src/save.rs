@@ -0,0 +1,111 @@
+use agb::save::{SaveData, SaveManager};
+use alloc::{string::String, vec, vec::Vec};
+use core::fmt::Write;
+use physics::{Ghost, Recording};
+use util::{slot_offset, SaveGame, REPLAY_OFFSET, SAVE_SLOTS, SLOT_SIZE};
+
+/// The cartridge has 32KiB of SRAM
+const SRAM_SIZE: usize = 32 * 1024;
+/// The best time attack run is kept at the end of save memory
+const GHOST_SIZE: usize = 12 * 1024;
+const GHOST_OFFSET: usize = SRAM_SIZE - GHOST_SIZE;
+/// The longest replay which fits between the slots and the ghost
+pub const MAX_REPLAY_SIZE: usize = GHOST_OFFSET - REPLAY_OFFSET;
+
+/// The save slots in the cartridge's battery-backed SRAM
+pub struct SaveSlots {
+    save_data: SaveData,
+}
+
+impl SaveSlots {
+    pub fn new(save_manager: &mut SaveManager) -> Self {
+        save_manager.init_sram();
+
+        Self {
+            save_data: save_manager
+                .access()
+                .expect("Should be able to access save memory"),
+        }
+    }
+
+    /// The game saved in each slot. Empty slots, and slots which can't be read, have no game.
+    pub fn load_all(&mut self) -> [Option<SaveGame>; SAVE_SLOTS] {
+        core::array::from_fn(|slot| self.load(slot))
+    }
+
+    fn load(&mut self, slot: usize) -> Option<SaveGame> {
+        let mut buffer = [0; SLOT_SIZE];
+        self.save_data.read(slot_offset(slot), &mut buffer).ok()?;
+
+        SaveGame::decode(&buffer).ok()
+    }
+
+    /// The last replay saved, if there is one which can be read
+    pub fn load_replay(&mut self) -> Option<Recording> {
+        Recording::decode(&self.read(REPLAY_OFFSET, MAX_REPLAY_SIZE)?).ok()
+    }
+
+    /// Whether the replay was saved. Replays which are too long to fit are left out, keeping the
+    /// last one which did.
+    pub fn save_replay(&mut self, replay: &[u8]) -> bool {
+        self.write(REPLAY_OFFSET, MAX_REPLAY_SIZE, replay)
+    }
+
+    /// The fastest time attack run, if there is one which can be read
+    pub fn load_ghost(&mut self) -> Option<Ghost> {
+        Ghost::decode(&self.read(GHOST_OFFSET, GHOST_SIZE)?).ok()
+    }
+
+    /// Whether the run was saved. Runs which are too long to fit are left out, keeping the last
+    /// one which did.
+    pub fn save_ghost(&mut self, ghost: &Ghost) -> bool {
+        self.write(GHOST_OFFSET, GHOST_SIZE, &ghost.encode())
+    }
+
+    fn read(&mut self, offset: usize, size: usize) -> Option<Vec<u8>> {
+        let mut buffer = vec![0; size];
+        self.save_data.read(offset, &mut buffer).ok()?;
+
+        Some(buffer)
+    }
+
+    /// Whether the bytes fit in the space and were written
+    fn write(&mut self, offset: usize, size: usize, bytes: &[u8]) -> bool {
+        if bytes.len() > size {
+            return false;
+        }
+
+        self.save_data
+            .prepare_write(offset..offset + bytes.len())
+            .and_then(|mut block| block.write_and_verify(offset, bytes))
+            .is_ok()
+    }
+
+    pub fn save(&mut self, slot: usize, game: &SaveGame) {
+        let offset = slot_offset(slot);
+
+        // a save which fails to write is caught by its checksum when it's next loaded, and
+        // there's nothing better to do than carry on playing
+        let _ = self
+            .save_data
+            .prepare_write(offset..offset + SLOT_SIZE)
+            .and_then(|mut block| block.write_and_verify(offset, &game.encode()));
+    }
+}
+
+/// Writes the replay to the mGBA log as hex, for the host replay tool to read back
+pub fn log_replay(replay: &[u8]) {
+    if agb::mgba::Mgba::new().is_none() {
+        return;
+    }
+
+    agb::println!("replay start");
+    for line in replay.chunks(32) {
+        let mut hex = String::with_capacity(line.len() * 2);
+        for byte in line {
+            let _ = write!(hex, "{byte:02x}");
+        }
+
+        agb::println!("replay: {hex}");
+    }
+}
@@ -1,34 +1,188 @@
+use core::{cmp::Ordering, mem};
+
 use alloc::boxed::Box;
+use map::Level;
+use physics::{Ghost, Recording};
+use util::{SaveGame, SAVE_SLOTS};
 
 mod game;
+mod menu_text;
+mod pause;
 mod state;
+mod title;
 
 pub use state::*;
 
-enum TransitionScene {}
+/// How many sixteenths of the way to black the screen is at the darkest point of a fade
+const BLACK: u8 = 16;
+
+enum TransitionScene {
+    Title,
+    /// Starts a new game on the transition's level
+    Game,
+    /// Plays back the transition's replay
+    Replay,
+    /// Races against the transition's ghost from the start of the first level
+    TimeAttack,
+    /// Holds the current scene while the pause menu is shown on top of it
+    Pause,
+    /// Carries on with the paused scene
+    Resume,
+}
 
 trait Scene {
     fn transition(&mut self, transition: &mut Transition) -> Option<TransitionScene>;
     fn update(&mut self, update: &mut Update);
     fn display(&mut self, display: &mut Display);
+
+    /// How many sixteenths of the way to black the backgrounds are while this scene is shown.
+    /// Sprites are left bright so that anything drawn on top stands out.
+    fn dim(&self) -> u8 {
+        0
+    }
 }
 
 impl SceneManager {
-    pub fn new() -> Self {
+    pub fn new(
+        level: &'static Level,
+        saves: [Option<SaveGame>; SAVE_SLOTS],
+        replay: Option<Recording>,
+        ghost: Option<Ghost>,
+    ) -> Self {
+        let transition = Transition::new(level, saves, replay, ghost);
+
         Self {
-            current_scene: Box::new(game::Game::new()),
+            current_scene: Box::new(title::Title::new(&transition)),
+            transition,
+            fading_to: None,
+            fade: BLACK,
+            fade_sprites: true,
         }
     }
 
+    /// The replay to watch from the title, once it has been saved
+    pub fn set_replay(&mut self, replay: Recording) {
+        self.transition.replay = Some(replay);
+    }
+
+    /// The new fastest time attack run, once it has been saved
+    pub fn set_best_run(&mut self, ghost: Ghost) {
+        self.transition.ghost = Some(ghost);
+    }
+
     pub fn frame(&mut self, update: &mut Update) {
+        if let Some(next_scene) = self.fading_to.take() {
+            if self.fade < BLACK {
+                // the scene being left is frozen while it fades out
+                self.fade += 1;
+                self.fading_to = Some(next_scene);
+                return;
+            }
+
+            self.switch_to(next_scene, update);
+        }
+
+        let dim = self.current_scene.dim();
+        self.fade = match self.fade.cmp(&dim) {
+            Ordering::Less => self.fade + 1,
+            Ordering::Equal => self.fade,
+            Ordering::Greater => self.fade - 1,
+        };
+        if self.fade <= dim {
+            self.fade_sprites = false;
+        }
+
         self.current_scene.update(update);
+        if let Some((slot, game)) = update.saved_game() {
+            self.transition.saves[slot] = Some(game);
+        }
+
+        match self.current_scene.transition(&mut self.transition) {
+            // the pause menu goes on top of the game, so there is nothing to fade out
+            Some(next_scene @ (TransitionScene::Pause | TransitionScene::Resume)) => {
+                self.switch_to(next_scene, update);
+            }
+            Some(next_scene) => {
+                self.fading_to = Some(next_scene);
+                self.fade_sprites = true;
+            }
+            None => {}
+        }
+    }
+
+    fn switch_to(&mut self, scene: TransitionScene, update: &mut Update) {
+        let level = self.transition.level;
+
+        self.current_scene = match scene {
+            TransitionScene::Title => {
+                self.transition.paused = None;
+                Box::new(title::Title::new(&self.transition))
+            }
+            TransitionScene::Game => {
+                self.transition.paused = None;
+                update.change_level(level);
+                Box::new(game::Game::new(
+                    level,
+                    self.transition.slot,
+                    self.transition.loaded.take(),
+                ))
+            }
+            TransitionScene::Replay => {
+                self.transition.paused = None;
+                let replay = self
+                    .transition
+                    .replay
+                    .clone()
+                    .expect("Should only watch the replay when there is one");
+                update.change_level(level);
+                Box::new(game::Game::replay(level, replay))
+            }
+            TransitionScene::TimeAttack => {
+                self.transition.paused = None;
+                update.change_level(level);
+                Box::new(game::Game::time_attack(
+                    level,
+                    self.transition.ghost.clone(),
+                ))
+            }
+            TransitionScene::Pause => {
+                let paused = mem::replace(&mut self.current_scene, Box::new(pause::Pause::new()));
+                self.transition.paused = Some(paused);
+                return;
+            }
+            TransitionScene::Resume => self
+                .transition
+                .paused
+                .take()
+                .expect("Should only resume when there is a paused scene"),
+        };
     }
 
     pub fn display(&mut self, display: &mut Display) {
         self.current_scene.display(display);
+
+        if let Some(paused) = self.transition.paused.as_mut() {
+            paused.display(display);
+        }
+    }
+
+    /// How far to fade the screen to black, and whether sprites should be faded along with the
+    /// backgrounds
+    pub fn fade(&self) -> Fade {
+        Fade {
+            amount: self.fade,
+            sprites: self.fade_sprites,
+        }
     }
 }
 
 pub struct SceneManager {
     current_scene: Box<dyn Scene>,
+    transition: Transition,
+    /// The scene which is switched to once the screen has faded to black
+    fading_to: Option<TransitionScene>,
+    fade: u8,
+    /// Sprites are faded along with everything else when going to and from black, but not when a
+    /// scene only dims the backgrounds
+    fade_sprites: bool,
 }
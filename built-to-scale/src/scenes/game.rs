@@ -5,433 +5,189 @@ use core::fmt::Write;
 use agb::{
     display::{
         affine::AffineMatrix,
-        object::{ObjectTextRender, PaletteVram, Size, Sprite, TextAlignment},
+        object::{ObjectTextRender, PaletteVram, Size, Sprite, Tag, TextAlignment},
         HEIGHT, WIDTH,
     },
-    fixnum::{num, Num, Rect, Vector2D},
+    fixnum::{Num, Rect, Vector2D},
 };
 
-use alloc::vec::Vec;
-use map::{Path, PowerUpKind};
-use powerups::PowerUpObject;
-use util::{Circle, Collider, Number};
+use alloc::{vec, vec::Vec};
+use map::Level;
+use physics::{
+    Event, Ghost, GhostFrame, GhostPlayback, GhostRecorder, Playback, Player, PlayerFacing,
+    PlayerInput, PlayerState, Pose, Recording, Simulation, Terrain,
+};
+use util::{
+    CameraTransform, InputSnapshot, InputSource, ItemSet, Number, RealSpace, SaveGame, ScrollSpring,
+};
 
 use crate::{
-    mission_logs::MISSION_LOGS,
     resources::{self, BUBBLE, BUBBLE_POP, FONT, TEXT_PALETTE},
+    save,
 };
 
 use super::{Scene, Update};
 
 struct Camera {
     position: Vector2D<Number>,
+    /// How fast the camera is being pulled back to a scroll stop it has gone past
+    scroll_velocity: Vector2D<Number>,
+    scroll_spring: ScrollSpring,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-enum PlayerFacing {
-    Left,
-    Right,
-}
-
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-enum JumpState {
-    HasJump,
-    Jumping,
-    Falling,
-}
-
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-enum GroundState {
-    OnGround,
-    InAir,
-}
-
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-enum DashState {
-    Available,
-    Used,
-}
-
-struct RecoveringState {
-    recover_to: Vector2D<Number>,
-    starting_from: Vector2D<Number>,
-    starting_reverse_local_gravity: Vector2D<Number>,
-    destination_reverse_local_gravity: Vector2D<Number>,
-    time: u32,
-}
-
-enum PlayerState {
-    Playing {
-        remaining_pop_time: u32,
-        pop_location: Vector2D<Number>,
-    },
-    Recovering(RecoveringState),
-}
-
-struct Player {
-    // the angle the player is facing, corresponding to local gravity
-    angle: AffineMatrix,
-    // the normal of the surface the player is on, not the gravity source
-    surface_normal: Vector2D<Number>,
-    speed: Vector2D<Number>,
-    position: Vector2D<Number>,
-    facing: PlayerFacing,
-    ground_state: GroundState,
-    ground_speed: Number,
-    air_speed: Number,
-
-    can_dash: bool,
-    dash_state: DashState,
-    max_jumps: usize,
-    jumps_remaining: usize,
-    jump_state: JumpState,
-    jump_speed: Number,
-
-    frame: usize,
-}
-
-impl Player {
-    fn update_facing(&mut self, direction: Vector2D<Number>) {
-        let target_angle = AffineMatrix {
-            a: -direction.y,
-            b: direction.x,
-            c: -direction.x,
-            d: -direction.y,
-            x: 0.into(),
-            y: 0.into(),
-        };
-
-        self.angle = target_angle;
-    }
-
-    fn get_normal(&self) -> Vector2D<Number> {
-        (self.angle.b, -self.angle.a).into()
+impl Camera {
+    fn transform(&self) -> CameraTransform {
+        CameraTransform::new(RealSpace(self.position), (WIDTH, HEIGHT).into())
     }
+}
 
-    fn handle_direction_input(&mut self, x: i32, is_dashing: bool, update: &mut Update) {
-        if x != 0 {
-            let dash =
-                if self.can_dash && is_dashing && self.dash_state == DashState::Available && x != 0
-                {
-                    self.dash_state = DashState::Used;
-                    update.play_sfx(resources::DASH_SOUND);
-                    num!(3.)
-                } else {
-                    num!(0.)
-                };
-
-            let (acceleration, normal) = if self.is_on_ground() {
-                if self.surface_normal.dot(self.get_normal()) > num!(0.7) {
-                    (
-                        Vector2D::new(0.into(), Number::new(x)) * (self.ground_speed + dash),
-                        self.surface_normal,
-                    )
-                } else {
-                    (
-                        Vector2D::new(0.into(), Number::new(x)) * (self.ground_speed + dash),
-                        self.get_normal(),
-                    )
-                }
-            } else {
-                (
-                    Vector2D::new(0.into(), Number::new(x)) * (self.air_speed + dash),
-                    self.get_normal(),
-                )
-            };
-
-            let rotated_acceleration = (
-                normal.x * acceleration.x - normal.y * acceleration.y,
-                normal.y * acceleration.x + normal.x * acceleration.y,
-            )
-                .into();
-
-            self.speed += rotated_acceleration;
-
-            if x < 0 {
-                self.facing = PlayerFacing::Left;
-            } else {
-                self.facing = PlayerFacing::Right;
-            }
-        }
-    }
-
-    /// returns whether or not the jump actually happened
-    fn handle_jump_input(&mut self) -> bool {
-        if self.jump_state == JumpState::HasJump {
-            let normal = self.get_normal();
-
-            let dot = self.speed.dot(normal);
-            if dot < 0.into() {
-                self.speed -= normal * dot;
-            }
-            self.speed += normal * self.jump_speed;
-
-            self.position += self.speed;
-
-            self.jump_state = JumpState::Jumping;
-            self.jumps_remaining -= 1;
-            self.frame = 0;
-
-            return true;
-        }
-
-        false
-    }
+/// The fraction of the distance to where a camera zone wants the camera which is covered each frame
+const CAMERA_ZONE_EASING: i32 = 8;
+/// The fastest the camera moves in pixels per frame when going to where a camera zone wants it
+const CAMERA_ZONE_SPEED: i32 = 4;
 
-    fn rendered_position(&self) -> Vector2D<Number> {
-        self.position - (8, 8).into()
-    }
+/// Rotates the player to stand up away from local gravity
+fn player_angle(player: &Player) -> AffineMatrix {
+    let up = player.get_normal();
 
-    fn frame(&mut self) {
-        self.frame = self.frame.wrapping_add(1);
-        if self.jump_state == JumpState::Jumping && self.frame > 32 {
-            if self.jumps_remaining > 0 {
-                self.jump_state = JumpState::HasJump;
-            } else {
-                self.jump_state = JumpState::Falling;
-            }
-        }
-    }
-
-    fn is_on_ground(&self) -> bool {
-        self.ground_state == GroundState::OnGround
+    AffineMatrix {
+        a: -up.y,
+        b: up.x,
+        c: -up.x,
+        d: -up.y,
+        x: 0.into(),
+        y: 0.into(),
     }
+}
 
-    fn sprite(&self) -> &'static Sprite {
-        match self.jump_state {
-            JumpState::HasJump => {
-                if self.speed.magnitude_squared() < num!(0.1) {
-                    resources::IDLE.sprite(0)
-                } else {
-                    resources::WALK.animation_sprite(self.frame / 8)
-                }
-            }
-            JumpState::Jumping => resources::JUMP.animation_sprite(self.frame / 16),
-            JumpState::Falling => resources::FALL.sprite(0),
-        }
+fn pose_sprite(pose: Pose) -> &'static Sprite {
+    match pose {
+        Pose::Idle => resources::IDLE.sprite(0),
+        Pose::Walking(step) => resources::WALK.animation_sprite(step),
+        Pose::Jumping(step) => resources::JUMP.animation_sprite(step),
+        Pose::Falling => resources::FALL.sprite(0),
     }
+}
 
-    fn apply_powerup(&mut self, powerup: PowerUpKind) {
-        match powerup {
-            PowerUpKind::JumpBoost => {
-                self.jump_speed = num!(3.5);
-            }
-            PowerUpKind::Dash => self.can_dash = true,
-            PowerUpKind::DoubleJump => {
-                self.max_jumps += 1;
-                self.jumps_remaining += 1;
-            }
-        }
-    }
+/// Where the game's buttons come from
+enum Controls {
+    /// The player, whose buttons are recorded so that the game can be watched back
+    Player(Recording),
+    /// A replay, which is checked against the simulation as it plays
+    Replay(Playback),
 }
 
 pub struct Game {
     game: GamePart,
-    terrain: Terrain,
     mission_log: MissionLogPlayer,
+    /// The level the player has reached the exit to
+    exit: Option<&'static Level>,
+    pause_pressed: bool,
+    /// The save slot progress is saved to
+    slot: usize,
+    /// In frames
+    play_time: u32,
+    controls: Controls,
+    /// The buttons for the last frame the game was played. Presses are worked out from this
+    /// rather than the buttons outside the game, so that pausing can't change how a replay plays.
+    input: InputSnapshot,
+    /// Only set when racing against the clock
+    time_attack: Option<TimeAttack>,
+    /// The replay or time attack is over, so it's back to the title
+    finished: bool,
+}
+
+/// Racing from the start of the level to the finish, against the best run so far
+struct TimeAttack {
+    run: GhostRecorder,
+    /// The best run so far, which is shown as a ghost
+    best: Option<GhostPlayback>,
+    /// Where the ghost is this frame, until the best run ends
+    ghost: Option<GhostFrame>,
 }
 
 struct GamePart {
+    simulation: Simulation,
     camera: Camera,
-    player: Player,
-    last_gravity_source: Option<Collider>,
-    player_state: PlayerState,
-
-    powerups: Vec<PowerUpObject>,
+    /// The bubble the player recovered in pops once they're back in control
+    remaining_pop_time: u32,
+    pop_location: Vector2D<Number>,
+
+    /// The point the player last recovered to
+    recovery_point: Option<Vector2D<Number>>,
+    /// Whether anything has happened this frame which should be saved
+    made_progress: bool,
+    /// The sprite for each of the level's power ups, looked up once from the names in the map
+    power_up_sprites: Vec<&'static Tag>,
 }
 
 impl GamePart {
-    pub fn new() -> Self {
+    pub fn new(level: &'static Level) -> Self {
         Self {
+            simulation: Simulation::new(level),
             camera: Camera {
-                position: map::CAMERA_START,
-            },
-            player: Player {
-                angle: AffineMatrix::identity(),
-                speed: (0, 0).into(),
-                position: map::START_POINT,
-                jump_state: JumpState::Falling,
-                facing: PlayerFacing::Right,
-                ground_state: GroundState::InAir,
-                surface_normal: (0, 0).into(),
-
-                jump_speed: num!(2.2),
-                ground_speed: num!(0.25),
-                air_speed: num!(0.0625),
-
-                can_dash: false,
-                dash_state: DashState::Available,
-                jumps_remaining: 1,
-                max_jumps: 1,
-
-                frame: 0,
-            },
-            last_gravity_source: None,
-            player_state: PlayerState::Playing {
-                remaining_pop_time: 0,
-                pop_location: (0, 0).into(),
+                position: level.camera_start,
+                scroll_velocity: (0, 0).into(),
+                scroll_spring: ScrollSpring::default(),
             },
+            remaining_pop_time: 0,
+            pop_location: (0, 0).into(),
 
-            powerups: map::POWER_UPS.iter().map(PowerUpObject::new).collect(),
+            recovery_point: None,
+            made_progress: false,
+            power_up_sprites: level
+                .power_ups
+                .iter()
+                .map(|power_up| resources::sprite_tag(power_up.sprite))
+                .collect(),
         }
     }
 
-    fn handle_direction_input(&mut self, x: i32, is_dashing: bool, update: &mut Update) {
-        self.player.handle_direction_input(x, is_dashing, update);
-    }
+    fn update(&mut self, update: &mut Update, input: &InputSnapshot) {
+        self.remaining_pop_time = self.remaining_pop_time.saturating_sub(1);
 
-    /// returns whether or not the jump actually happened
-    fn handle_jump_input(&mut self) -> bool {
-        self.player.handle_jump_input()
-    }
+        let input = PlayerInput::from(input);
 
-    fn handle_player_death(&mut self, update: &mut Update, terrain: &Terrain) {
-        update.play_sfx(resources::RECOVERY_SOUND);
-
-        let point_to_recover_to = map::get_recovery_point(self.player.position);
-        self.player_state = PlayerState::Recovering(RecoveringState {
-            recover_to: point_to_recover_to,
-            starting_from: self.player.position,
-            starting_reverse_local_gravity: (self.player.position
-                - self
-                    .last_gravity_source
-                    .as_ref()
-                    .unwrap()
-                    .closest_point(self.player.position))
-            .fast_normalise(),
-            destination_reverse_local_gravity: (point_to_recover_to
-                - get_gravity_source(terrain.colliders(point_to_recover_to), point_to_recover_to)
-                    .1)
-                .fast_normalise(),
-            time: 0,
-        });
-    }
+        for event in self.simulation.frame(&input).iter() {
+            match event {
+                Event::Jumped => update.play_sfx(resources::JUMP_SOUND),
+                Event::Dashed => update.play_sfx(resources::DASH_SOUND),
+                Event::Landed => update.play_sfx(resources::LAND_GROUND),
+                Event::Died => {
+                    update.play_sfx(resources::RECOVERY_SOUND);
 
-    /// returns the cosine of the smallest angle of collision if there is one. So None = not touching the ground
-    fn handle_collider_collisions(
-        &mut self,
-        update: &mut Update,
-        colliders: DynamicAndStaticColliders,
-        terrain: &Terrain,
-    ) -> Option<Number> {
-        let mut max_angle = None;
-
-        for collider in colliders.iter() {
-            let player_circle = Circle {
-                position: self.player.position,
-                radius: 8.into(),
-            };
-            if collider.collides_circle(&player_circle) {
-                if collider.tag.is_kills_player() {
-                    self.handle_player_death(update, terrain);
-                } else if collider.tag.is_collision() {
-                    let normal = collider.normal_circle(&player_circle);
-
-                    self.player.surface_normal = normal;
-
-                    let dot = normal.dot(self.player.speed);
-                    if dot < 0.into() {
-                        self.player.speed -= normal * dot;
+                    if let PlayerState::Recovering(recovering) =
+                        &self.simulation.physics.player_state
+                    {
+                        self.recovery_point = Some(recovering.recover_to);
+                        self.made_progress = true;
                     }
-
-                    let cosine_of_floor_angle = self.player.get_normal().dot(normal);
-                    // 0.7 is approximately sqrt(2) / 2 which is about 45 degrees
-                    max_angle = Some(max_angle.unwrap_or(num!(-1.)).max(cosine_of_floor_angle));
-
-                    let overshoot = collider.overshoot(&player_circle);
-
-                    self.player.position += overshoot + collider.velocity;
                 }
-            }
-        }
-
-        max_angle
-    }
-
-    fn get_gravity_source(&mut self, colliders: DynamicAndStaticColliders) -> Vector2D<Number> {
-        if colliders.is_empty() {
-            let source = self
-                .last_gravity_source
-                .as_ref()
-                .expect("We should have a gravity source if we're in empty space");
-            source.closest_point(self.player.position)
-        } else {
-            let (gravity_source_collider, gravity_source_position) =
-                get_gravity_source(colliders, self.player.position);
-
-            self.last_gravity_source = Some(gravity_source_collider.clone());
-            gravity_source_position
-        }
-    }
-
-    fn physics_frame(&mut self, update: &mut Update, terrain: &Terrain) {
-        let colliders = terrain.colliders(self.player.position);
-        let gravity_source = self.get_gravity_source(colliders);
-
-        let gravity_direction = (gravity_source - self.player.position).fast_normalise();
-
-        let gravity = if self.player.jump_state == JumpState::Jumping && update.jump_pressed() {
-            gravity_direction / 128
-        } else {
-            gravity_direction / 10
-        };
-
-        let old_speed = self.player.speed;
-        let was_on_ground = self.player.is_on_ground();
-
-        self.player.speed += gravity;
-        self.player.position += self.player.speed;
-
-        self.player.ground_state = match self.handle_collider_collisions(update, colliders, terrain)
-        {
-            Some(value) => {
-                if value > num!(0.8) {
-                    // approximately < 45 degree angle. So definitely on the ground
-                    self.player.jump_state = JumpState::HasJump;
-                    self.player.dash_state = DashState::Available;
-                    self.player.jumps_remaining = self.player.max_jumps;
-
-                    // Apply a reasonably high amount of friction
-                    self.player.speed *= num!(0.8);
-
-                    GroundState::OnGround
-                } else if value > num!(0.7) {
-                    // just over 45 degrees (since 0.7 ~= sqrt(2) / 2). Still should be considered ground, but apply less friction
-                    self.player.jump_state = JumpState::HasJump; // should allow for another jump
-                    self.player.dash_state = DashState::Available;
-                    self.player.jumps_remaining = self.player.max_jumps;
-                    self.player.speed *= num!(0.90);
-
-                    GroundState::OnGround
-                } else {
-                    // hit something which isn't floor-like
-                    self.player.speed *= num!(0.95);
-                    GroundState::InAir
+                Event::Recovered => {
+                    self.remaining_pop_time = BUBBLE_POP.sprites().len() as u32 * 2;
+                    self.pop_location = self.simulation.player().position;
                 }
+                Event::PowerUpTouched => update.play_sfx(resources::POWER_UP_SOUND),
+                Event::PowerUpCollected => self.made_progress = true,
             }
-            None => {
-                // hit something which isn't floor-like
-                self.player.speed *= num!(0.95);
-                GroundState::InAir
-            }
-        };
-
-        let is_on_ground = self.player.is_on_ground();
-        if !was_on_ground && is_on_ground && old_speed.dot(gravity_direction) > 1.into() {
-            update.play_sfx(resources::LAND_GROUND);
         }
 
-        if self.player.speed.magnitude_squared() < num!(0.005) {
-            self.player.speed = (0, 0).into();
-        }
+        self.update_camera();
+        update.set_pos(self.camera.transform().top_left());
 
-        self.player.update_facing(-gravity_direction);
+        if self.simulation.player().position.y < (-140).into() {
+            update.play_space_music();
+        }
     }
 
     fn update_camera(&mut self) {
         let camera_size = (64, 32).into();
+        let level = self.simulation.physics.level;
+        let player = self.simulation.player();
+        let zone = level.get_camera_zone(player.position);
+        let target_position = player.position + player.get_normal() * 32 + player.speed * 64;
         let target_position =
-            self.player.position + self.player.get_normal() * 32 + self.player.speed * 64;
+            zone.map_or(target_position, |zone| zone.clamp_camera(target_position));
         let camera_rect = Rect::new(self.camera.position - camera_size / 2, camera_size);
 
         let camera_destination = if !camera_rect.contains_point(target_position) {
@@ -445,144 +201,53 @@ impl GamePart {
             self.camera.position
         };
 
-        let camera_destination = if let Some(scroll_stop) = map::get_scroll_stop(
-            self.camera.position.x.floor(),
-            self.camera.position.y.floor(),
-        ) {
-            let mut camera_destination = camera_destination;
-            if let Some(x_min) = scroll_stop.minimum_x {
-                if self.camera.position.x >= x_min {
-                    camera_destination.x = camera_destination.x.max(x_min);
-                }
-            }
-            if let Some(y_min) = scroll_stop.minimum_y {
-                if self.camera.position.y >= y_min {
-                    camera_destination.y = camera_destination.y.max(y_min);
-                }
-            }
-            if let Some(x_max) = scroll_stop.maximum_x {
-                if self.camera.position.x <= x_max {
-                    camera_destination.x = camera_destination.x.min(x_max);
-                }
-            }
-            if let Some(y_max) = scroll_stop.maximum_y {
-                if self.camera.position.y <= y_max {
-                    camera_destination.y = camera_destination.y.min(y_max);
-                }
+        let camera_destination = level.apply_scroll_stop(
+            self.camera.position,
+            camera_destination,
+            &mut self.camera.scroll_velocity,
+            &self.camera.scroll_spring,
+        );
+
+        // move towards where the zone wants the camera over a few frames, rather than jumping
+        // there as soon as the player crosses into it
+        let camera_destination = if let Some(zone) = zone {
+            let mut correction =
+                (zone.clamp_camera(camera_destination) - camera_destination) / CAMERA_ZONE_EASING;
+            if correction.magnitude_squared() > (CAMERA_ZONE_SPEED * CAMERA_ZONE_SPEED).into() {
+                correction = correction.normalise() * CAMERA_ZONE_SPEED;
             }
 
-            camera_destination
+            camera_destination + correction
         } else {
             camera_destination
         };
 
         self.camera.position = camera_destination;
     }
-}
-
-fn get_gravity_source(
-    colliders: DynamicAndStaticColliders<'_>,
-    position: Vector2D<Number>,
-) -> (&Collider, Vector2D<Number>) {
-    colliders
-        .iter()
-        .filter(|x| x.tag.is_gravitational())
-        .map(|collider| (collider, collider.closest_point(position)))
-        .min_by_key(|&(_, closest_point)| (closest_point - position).magnitude_squared())
-        .unwrap()
-}
-
-impl GamePart {
-    fn update(&mut self, update: &mut Update, terrain: &Terrain) {
-        match &mut self.player_state {
-            PlayerState::Playing {
-                remaining_pop_time, ..
-            } => {
-                *remaining_pop_time = remaining_pop_time.saturating_sub(1);
-
-                let button_press = update.button_x_tri();
-                self.handle_direction_input(button_press as i32, update.is_dash_pressed(), update);
-                self.physics_frame(update, terrain);
-
-                if update.jump_just_pressed() && self.handle_jump_input() {
-                    update.play_sfx(resources::JUMP_SOUND);
-                }
-
-                self.player.frame();
-            }
-            PlayerState::Recovering(recover) => {
-                recover.time += 1;
-                match recover.time {
-                    0..16 => {
-                        self.player.speed = (0, 0).into();
-                    }
-                    16..64 => {
-                        let time = recover.time as i32 - 16;
-                        let time = Number::new(time) / (64 - 16);
-
-                        let start_position_line = recover.starting_from
-                            + recover.starting_reverse_local_gravity * time * 30;
-                        let ending_position_line = recover.recover_to
-                            + recover.destination_reverse_local_gravity * (-time + 1) * 30;
-
-                        let position =
-                            start_position_line * (-time + 1) + ending_position_line * time;
 
-                        self.player.position = position;
-                    }
-                    64..80 => {}
-                    80.. => {
-                        self.player_state = PlayerState::Playing {
-                            remaining_pop_time: BUBBLE_POP.sprites().len() as u32 * 2,
-                            pop_location: self.player.position,
-                        }
-                    }
-                }
-            }
-        }
-
-        self.update_camera();
-        update.set_pos(
-            (self.camera.position + (num!(0.5), num!(0.5)).into()).floor()
-                - (WIDTH / 2, HEIGHT / 2).into(),
-        );
-
-        self.powerups.retain_mut(|powerup| {
-            if let Some(powerup) = powerup.update(self.player.position, update) {
-                self.player.apply_powerup(powerup);
-
-                return false;
-            }
-
-            true
-        });
+    fn display(&mut self, display: &mut super::Display) {
+        let camera = self.camera.transform();
 
-        if self.player.position.y < (-140).into() {
-            update.play_space_music();
-        }
-    }
+        let player = self.simulation.player();
 
-    fn display(&mut self, display: &mut super::Display) {
-        match &self.player_state {
-            PlayerState::Playing {
-                remaining_pop_time,
-                pop_location,
-            } => {
+        match &self.simulation.physics.player_state {
+            PlayerState::Playing => {
                 display.display(
-                    self.player.sprite(),
-                    &self.player.angle,
-                    self.player.rendered_position() - self.camera.position,
-                    self.player.facing != PlayerFacing::Right,
+                    pose_sprite(player.pose()),
+                    &player_angle(player),
+                    camera.to_screen(RealSpace(player.rendered_position())),
+                    player.facing != PlayerFacing::Right,
                 );
 
-                if *remaining_pop_time > 0 {
-                    let idx =
-                        ((BUBBLE_POP.sprites().len() as i32 * 2 - *remaining_pop_time as i32) / 2)
-                            .try_into()
-                            .unwrap_or_default();
+                if self.remaining_pop_time > 0 {
+                    let idx = ((BUBBLE_POP.sprites().len() as i32 * 2
+                        - self.remaining_pop_time as i32)
+                        / 2)
+                    .try_into()
+                    .unwrap_or_default();
                     display.display_regular(
                         BUBBLE_POP.animation_sprite(idx),
-                        *pop_location - (16, 16).into() - self.camera.position,
+                        camera.to_screen(RealSpace(self.pop_location - (16, 16).into())),
                     );
                 }
             }
@@ -590,200 +255,303 @@ impl GamePart {
                 let idx = state.time as usize / 2;
                 display.display_regular(
                     BUBBLE.animation_sprite(idx),
-                    self.player.position - (16, 16).into() - self.camera.position,
+                    camera.to_screen(RealSpace(player.position - (16, 16).into())),
                 );
             }
         }
 
-        for powerup in self.powerups.iter() {
-            powerup.display(self.camera.position, display);
+        for pickup in self.simulation.power_ups.iter() {
+            powerups::display(
+                pickup,
+                self.power_up_sprites[pickup.index()],
+                &camera,
+                display,
+            );
         }
     }
 }
 
 impl Game {
-    pub fn new() -> Self {
-        Self {
-            game: GamePart::new(),
-            terrain: Terrain {
-                loaded_dynamic_colliders: Vec::new(),
-            },
-            mission_log: MissionLogPlayer::new(),
-        }
-    }
-}
+    /// A game on the level, carrying on from the save if there is one
+    pub fn new(level: &'static Level, slot: usize, save: Option<SaveGame>) -> Self {
+        let game = GamePart::new(level);
+        // new games are loaded like any other, so that replays start the same way
+        let start = save.unwrap_or(SaveGame {
+            level: level_index(level),
+            stats: game.simulation.player().stats(),
+            collected_power_ups: ItemSet::default(),
+            seen_mission_logs: ItemSet::default(),
+            recovery_point: None,
+            play_time: 0,
+        });
 
-impl Scene for Game {
-    fn transition(&mut self, transition: &mut super::Transition) -> Option<super::TransitionScene> {
-        None
-    }
+        let mut game = Self {
+            game,
+            mission_log: MissionLogPlayer::new(level),
+            exit: None,
+            pause_pressed: false,
+            slot,
+            play_time: 0,
+            controls: Controls::Player(Recording::new(start)),
+            input: InputSnapshot::default(),
+            time_attack: None,
+            finished: false,
+        };
+        game.load(&start);
 
-    fn update(&mut self, update: &mut Update) {
-        self.terrain.update(self.game.player.position);
-        self.game.update(update, &self.terrain);
-        self.mission_log.update(self.game.player.position);
+        game
     }
 
-    fn display(&mut self, display: &mut super::Display) {
-        self.mission_log.display(display);
-        self.game.display(display);
-        self.terrain.display(display, self.game.camera.position);
+    /// Watches a replay on the level it was recorded on. Nothing is saved while it plays.
+    pub fn replay(level: &'static Level, recording: Recording) -> Self {
+        let mut game = Self::new(level, 0, Some(recording.start));
+
+        let playback = recording.playback();
+        game.input = playback.initial_snapshot();
+        game.controls = Controls::Replay(playback);
+
+        game
     }
-}
 
-#[derive(Copy, Clone)]
-struct DynamicAndStaticColliders<'a> {
-    static_colliders: &'static [&'static Collider],
-    dynamic_colliders: &'a [DynamicCollider],
-}
+    /// A new game on the level, which is over once the finish or an exit is reached. Nothing is
+    /// saved to the save slots, but the run is kept if it's the fastest yet.
+    pub fn time_attack(level: &'static Level, best: Option<Ghost>) -> Self {
+        let mut game = Self::new(level, 0, None);
 
-impl<'a> DynamicAndStaticColliders<'a> {
-    fn iter(&self) -> impl Iterator<Item = &'a Collider> {
-        self.static_colliders.iter().copied().chain(
-            self.dynamic_colliders
-                .iter()
-                .flat_map(|x| x.colliders.iter()),
-        )
+        let level = level_index(level);
+        game.time_attack = Some(TimeAttack {
+            run: GhostRecorder::new(level),
+            best: best
+                .filter(|best| best.level() == level)
+                .map(Ghost::playback),
+            ghost: None,
+        });
+
+        game
     }
 
-    fn is_empty(&self) -> bool {
-        self.static_colliders.is_empty()
-            && !self
-                .dynamic_colliders
-                .iter()
-                .any(|x| x.colliders[0].tag.is_gravitational())
+    fn load(&mut self, save: &SaveGame) {
+        let game = &mut self.game;
+
+        game.simulation.restore(save);
+        if let Some(point) = save.recovery_point {
+            game.recovery_point = Some(point);
+            game.camera.position = point;
+        }
+
+        self.mission_log.mark_seen(&save.seen_mission_logs);
+        self.play_time = save.play_time;
     }
-}
 
-struct DynamicCollider {
-    path: &'static Path,
-    current_path_element_idx: usize,
-    current_position: Vector2D<Number>,
-    colliders: Vec<Collider>,
-    direction: PathDirection,
-    path_index_timer: Num<i32, 24>,
-}
+    fn save_game(&self) -> SaveGame {
+        SaveGame {
+            level: level_index(self.game.simulation.physics.level),
+            stats: self.game.simulation.player().stats(),
+            collected_power_ups: self.game.simulation.collected_power_ups(),
+            seen_mission_logs: self.mission_log.seen(),
+            recovery_point: self.game.recovery_point,
+            play_time: self.play_time,
+        }
+    }
 
-enum PathDirection {
-    Forwards,
-    Backwards,
-}
+    /// The game to carry on with in the level being left for. The stats and play time carry on,
+    /// but which power ups and mission logs have been seen is only kept for the level being
+    /// played, so those are intentionally dropped along with the recovery point.
+    fn entering(&self, level: &'static Level) -> SaveGame {
+        SaveGame {
+            level: level_index(level),
+            collected_power_ups: ItemSet::default(),
+            seen_mission_logs: ItemSet::default(),
+            recovery_point: None,
+            ..self.save_game()
+        }
+    }
 
-struct Terrain {
-    loaded_dynamic_colliders: Vec<DynamicCollider>,
-}
+    /// Keeps the run if it's the fastest yet
+    fn finish_time_attack(&mut self, time_attack: TimeAttack, update: &mut Update) {
+        let run = time_attack.run.finish();
+        let best = time_attack.best.map(|best| best.ghost().frames());
 
-impl Terrain {
-    fn colliders(&self, position: Vector2D<Number>) -> DynamicAndStaticColliders {
-        DynamicAndStaticColliders {
-            static_colliders: map::get_nearby(position.x.floor(), position.y.floor()),
-            dynamic_colliders: &self.loaded_dynamic_colliders,
+        if best.map_or(true, |best| run.frames() < best) {
+            update.save_ghost(run);
         }
+
+        self.finished = true;
     }
 
-    fn load_paths(&mut self, player_position: Vector2D<Number>) {
-        let should_be_loaded_paths =
-            map::get_paths(player_position.x.floor(), player_position.y.floor());
-        // remove non active paths
-        self.loaded_dynamic_colliders.retain(|x| {
-            should_be_loaded_paths
-                .iter()
-                .any(|&p| core::ptr::eq(x.path, p))
-        });
-        let paths_to_load: Vec<_> = should_be_loaded_paths
+    /// Leaves for another level if the player has reached one of this level's exits
+    fn check_exits(&mut self) {
+        let position = self.game.simulation.player().position.floor();
+        let exit = self
+            .game
+            .simulation
+            .physics
+            .level
+            .exits
             .iter()
-            .copied()
-            .filter(|&path| {
-                !self
-                    .loaded_dynamic_colliders
-                    .iter()
-                    .any(|x| core::ptr::eq(x.path, path))
-            })
-            .collect();
-
-        // load now active paths
-        for to_be_loaded in paths_to_load {
-            let colliders = to_be_loaded.colliders.to_vec();
-            self.loaded_dynamic_colliders.push(DynamicCollider {
-                path: to_be_loaded,
-                current_path_element_idx: 0,
-                colliders,
-                current_position: to_be_loaded.points[0].point,
-                path_index_timer: 0.into(),
-                direction: PathDirection::Forwards,
-            });
+            .find(|exit| (exit.point - position).magnitude_squared() < exit.radius * exit.radius);
+
+        self.exit = exit.and_then(|exit| Level::by_name(exit.level));
+    }
+}
+
+impl Scene for Game {
+    fn transition(&mut self, transition: &mut super::Transition) -> Option<super::TransitionScene> {
+        let replaying = matches!(self.controls, Controls::Replay(_));
+        if self.finished || (replaying && self.exit.is_some()) {
+            return Some(super::TransitionScene::Title);
+        }
+
+        if let Some(level) = self.exit.take() {
+            transition.level = level;
+            transition.loaded = Some(self.entering(level));
+            return Some(super::TransitionScene::Game);
         }
+
+        self.pause_pressed.then_some(super::TransitionScene::Pause)
     }
 
-    fn update_paths(&mut self) {
-        for loaded in self.loaded_dynamic_colliders.iter_mut() {
-            let (from, to, frames) = match loaded.direction {
-                PathDirection::Forwards => {
-                    let from = &loaded.path.points[loaded.current_path_element_idx];
-                    let to = &loaded.path.points
-                        [(loaded.current_path_element_idx + 1) % loaded.path.points.len()];
-                    (from.point, to.point, from.incrementer)
+    fn update(&mut self, update: &mut Update) {
+        self.input = match &mut self.controls {
+            Controls::Player(recording) => {
+                // buttons held since before the game started aren't pressed in it
+                if recording.frames() == 0 {
+                    self.input = InputSnapshot::default().next(update.input().previous);
                 }
-                PathDirection::Backwards => {
-                    let from = &loaded.path.points[loaded.current_path_element_idx];
-                    let to = &loaded.path.points[(loaded.current_path_element_idx as isize - 1)
-                        .rem_euclid(loaded.path.points.len() as isize)
-                        as usize];
-                    (from.point, to.point, to.incrementer)
+
+                self.input.next(update.input().held)
+            }
+            Controls::Replay(playback) => playback.next_snapshot(self.input),
+        };
+
+        self.game.update(update, &self.input);
+        let saw_mission_log = self
+            .mission_log
+            .update(self.game.simulation.player().position);
+        self.check_exits();
+        self.pause_pressed = update.pause_just_pressed();
+
+        self.play_time = self.play_time.saturating_add(1);
+
+        let made_progress = core::mem::take(&mut self.game.made_progress);
+
+        match &mut self.controls {
+            // the rest of the game isn't recorded once the replay would no longer fit in save memory
+            Controls::Player(recording) => {
+                if recording.has_room(save::MAX_REPLAY_SIZE) {
+                    recording.record(&self.input, &self.game.simulation);
                 }
-            };
-
-            loaded.path_index_timer += frames;
-            if loaded.path_index_timer >= 1.into() {
-                loaded.path_index_timer -= 1;
-                match loaded.direction {
-                    PathDirection::Forwards => {
-                        loaded.current_path_element_idx += 1;
-                        if !loaded.path.complete {
-                            if loaded.current_path_element_idx == loaded.path.points.len() - 1 {
-                                loaded.direction = PathDirection::Backwards;
-                            }
-                        } else if loaded.current_path_element_idx == loaded.path.points.len() {
-                            loaded.current_path_element_idx = 0;
-                        }
-                    }
-                    PathDirection::Backwards => {
-                        loaded.current_path_element_idx -= 1;
-                        if loaded.current_path_element_idx == 0 {
-                            loaded.direction = PathDirection::Forwards;
-                        }
-                    }
+            }
+            Controls::Replay(playback) => match playback.check(&self.game.simulation) {
+                Ok(()) => self.finished = playback.is_finished(),
+                Err(desync) => {
+                    agb::println!(
+                        "Replay desynced on frame {}: expected hash {:08x} but got {:08x}",
+                        desync.frame,
+                        desync.expected,
+                        desync.actual
+                    );
+                    self.finished = true;
                 }
-            } else {
-                let next_position = from * (-loaded.path_index_timer + 1).change_base()
-                    + to * loaded.path_index_timer.change_base();
-                let velocity = next_position - loaded.current_position;
-                loaded.current_position += velocity;
-                for collider in loaded.colliders.iter_mut() {
-                    collider.apply_velocity(velocity);
+            },
+        }
+
+        // the play time is kept up to date by saving whenever the game is paused
+        let saves_progress =
+            matches!(self.controls, Controls::Player(_)) && self.time_attack.is_none();
+        if saves_progress && (made_progress || saw_mission_log || self.pause_pressed) {
+            update.save_game(self.slot, self.save_game());
+        }
+
+        if let Controls::Player(recording) = &mut self.controls {
+            if self.pause_pressed || self.exit.is_some() {
+                if !recording.has_room(save::MAX_REPLAY_SIZE) {
+                    agb::println!(
+                        "Replay is too long to save, only its first {} frames are kept",
+                        recording.frames()
+                    );
                 }
+
+                // a game which has been left doesn't need its recording any more, so it's handed
+                // over rather than copied
+                let replay = if self.exit.is_some() {
+                    let start = recording.start;
+                    core::mem::replace(recording, Recording::new(start))
+                } else {
+                    recording.clone()
+                };
+                update.save_replay(replay);
             }
         }
-    }
 
-    fn update(&mut self, player_position: Vector2D<Number>) {
-        self.load_paths(player_position);
-        self.update_paths();
+        if let Some(time_attack) = self.time_attack.as_mut() {
+            time_attack
+                .run
+                .record(GhostFrame::of(self.game.simulation.player()));
+            time_attack.ghost = time_attack.best.as_mut().and_then(Iterator::next);
+        }
+
+        if self.exit.is_some() || self.game.simulation.has_finished() {
+            if let Some(time_attack) = self.time_attack.take() {
+                self.finish_time_attack(time_attack, update);
+            }
+        }
     }
 
-    fn display(&self, display: &mut super::Display, camera_position: Vector2D<Number>) {
-        let camera = Rect::new(
-            camera_position - (WIDTH / 2 + 32, HEIGHT / 2 + 32).into(),
-            (WIDTH + 64, HEIGHT + 64).into(),
+    fn display(&mut self, display: &mut super::Display) {
+        self.mission_log.display(display);
+        self.game.display(display);
+
+        // drawn after the player, so that the player is in front of it
+        if let Some(ghost) = self
+            .time_attack
+            .as_ref()
+            .and_then(|time_attack| time_attack.ghost)
+        {
+            display_ghost(ghost, display, &self.game.camera.transform());
+        }
+
+        display_terrain(
+            &self.game.simulation.terrain,
+            display,
+            &self.game.camera.transform(),
         );
-        for collider in self.loaded_dynamic_colliders.iter() {
-            if camera.contains_point(collider.current_position) {
-                let image = convert_sprite(collider.path.image);
+    }
+}
+
+fn display_ghost(ghost: GhostFrame, display: &mut super::Display, camera: &CameraTransform) {
+    let position = RealSpace(ghost.position.change_base());
+    if !camera.is_on_screen(position, 32) {
+        return;
+    }
+
+    display.display_translucent(
+        pose_sprite(ghost.pose),
+        &AffineMatrix::from_rotation(Num::<i32, 8>::from_raw(ghost.angle.into())),
+        camera.to_screen(position),
+        ghost.facing != PlayerFacing::Right,
+    );
+}
+
+/// Where the level is in the list of levels, which is how saves refer to it
+fn level_index(level: &'static Level) -> u8 {
+    map::LEVELS
+        .iter()
+        .position(|&other| core::ptr::eq(other, level))
+        .expect("Should be playing one of the levels") as u8
+}
+
+fn display_terrain(terrain: &Terrain, display: &mut super::Display, camera: &CameraTransform) {
+    for collider in terrain.dynamic_colliders() {
+        for body_image in collider.body.images {
+            let position = collider.current_position + body_image.offset;
+            if camera.is_on_screen(RealSpace(position), 32) {
+                let image = convert_sprite(body_image.image);
                 let image_size = image.size().to_width_height();
                 let image_size = Vector2D::new(image_size.0 as i32, image_size.1 as i32);
                 display.display_regular(
                     image,
-                    collider.current_position - camera_position - image_size.change_base() / 2,
+                    camera.to_screen(RealSpace(position - image_size.change_base() / 2)),
                 );
             }
         }
@@ -804,23 +572,43 @@ fn convert_sprite(sprite: map::DynamicColliderImage) -> &'static Sprite {
 }
 
 struct MissionLogPlayer {
+    mission_logs: &'static [map::MissionLog],
     playing_mission_log: Option<ObjectTextRender<'static>>,
     currently_playing_mission_log_timer: u32,
-    encountered_mission_logs: u32,
+    encountered_mission_logs: Vec<bool>,
     palette: PaletteVram,
 }
 
 impl MissionLogPlayer {
-    fn new() -> Self {
+    fn new(level: &'static Level) -> Self {
         Self {
+            mission_logs: level.mission_logs,
             playing_mission_log: None,
             currently_playing_mission_log_timer: 0,
-            encountered_mission_logs: 0,
+            encountered_mission_logs: vec![false; level.mission_logs.len()],
             palette: PaletteVram::new(&TEXT_PALETTE).unwrap(),
         }
     }
 
-    fn update(&mut self, player_position: Vector2D<Number>) {
+    fn mark_seen(&mut self, seen: &ItemSet) {
+        for (idx, encountered) in self.encountered_mission_logs.iter_mut().enumerate() {
+            *encountered = seen.contains(idx);
+        }
+    }
+
+    fn seen(&self) -> ItemSet {
+        let mut seen = ItemSet::default();
+        for (idx, &encountered) in self.encountered_mission_logs.iter().enumerate() {
+            if encountered {
+                seen.insert(idx);
+            }
+        }
+
+        seen
+    }
+
+    /// Returns whether a mission log was encountered for the first time
+    fn update(&mut self, player_position: Vector2D<Number>) -> bool {
         let floored = player_position.floor();
         if let Some(playing_log) = self.playing_mission_log.as_mut() {
             playing_log.next_letter_group();
@@ -830,13 +618,13 @@ impl MissionLogPlayer {
                 self.playing_mission_log = None;
             }
         } else {
-            let active = MISSION_LOGS.iter().enumerate().find(|(idx, x)| {
-                self.encountered_mission_logs & (1 << idx) == 0
-                    && (x.point - floored).magnitude_squared() < 64 * 64
+            let active = self.mission_logs.iter().enumerate().find(|(idx, x)| {
+                !self.encountered_mission_logs[*idx]
+                    && (x.point - floored).magnitude_squared() < x.radius * x.radius
             });
             if let Some((idx, log)) = active {
                 // mark as encountered
-                self.encountered_mission_logs |= 1 << idx;
+                self.encountered_mission_logs[idx] = true;
 
                 self.currently_playing_mission_log_timer = 0;
 
@@ -847,8 +635,12 @@ impl MissionLogPlayer {
 
                 render.layout((WIDTH / 2, HEIGHT), TextAlignment::Left, 3);
                 self.playing_mission_log = Some(render);
+
+                return true;
             }
         }
+
+        false
     }
 
     fn display(&mut self, display: &mut super::Display) {
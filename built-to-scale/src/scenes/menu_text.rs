@@ -0,0 +1,54 @@
+use core::fmt::Write;
+
+use agb::display::{
+    object::{ObjectTextRender, PaletteVram, Size, TextAlignment},
+    HEIGHT, WIDTH,
+};
+use alloc::{format, string::String};
+
+use crate::resources::{FONT, TEXT_PALETTE};
+
+use super::Display;
+
+/// Text centred across the screen, shown a line at a time
+pub struct MenuText {
+    render: ObjectTextRender<'static>,
+    y: i32,
+}
+
+impl MenuText {
+    /// Text with its top `y` pixels down the screen
+    pub fn new(text: &str, y: i32) -> Self {
+        let palette = PaletteVram::new(&TEXT_PALETTE).unwrap();
+        let mut render = ObjectTextRender::new(&FONT, Size::S32x32, palette);
+
+        let _ = render.write_str(text);
+        let _ = render.write_char('\n');
+
+        render.layout((WIDTH, HEIGHT - y), TextAlignment::Center, 3);
+
+        Self { render, y }
+    }
+
+    pub fn update(&mut self) {
+        self.render.next_line();
+        self.render.update((0, self.y));
+    }
+
+    pub fn display(&mut self, display: &mut Display) {
+        self.render.commit(display.oam());
+    }
+}
+
+/// A time in frames as minutes, seconds and hundredths
+pub fn format_time(frames: u32) -> String {
+    let hundredths = frames * 100 / 60;
+    let seconds = hundredths / 100;
+
+    format!(
+        "{}:{:02}.{:02}",
+        seconds / 60,
+        seconds % 60,
+        hundredths % 100
+    )
+}
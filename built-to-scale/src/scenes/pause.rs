@@ -0,0 +1,45 @@
+use agb::display::HEIGHT;
+
+use super::{menu_text::MenuText, Display, Scene, Transition, TransitionScene, Update};
+
+/// How many sixteenths of the way to black the game is darkened while it is paused
+const PAUSE_DIM: u8 = 10;
+
+/// Shown on top of the paused game, which is kept in the transition until it is resumed
+pub struct Pause {
+    text: MenuText,
+    next_scene: Option<TransitionScene>,
+}
+
+impl Pause {
+    pub fn new() -> Self {
+        Self {
+            text: MenuText::new("Paused\nStart to carry on\nSelect to quit", HEIGHT / 3),
+            next_scene: None,
+        }
+    }
+}
+
+impl Scene for Pause {
+    fn transition(&mut self, _transition: &mut Transition) -> Option<TransitionScene> {
+        self.next_scene.take()
+    }
+
+    fn update(&mut self, update: &mut Update) {
+        self.text.update();
+
+        if update.pause_just_pressed() {
+            self.next_scene = Some(TransitionScene::Resume);
+        } else if update.quit_just_pressed() {
+            self.next_scene = Some(TransitionScene::Title);
+        }
+    }
+
+    fn display(&mut self, display: &mut Display) {
+        self.text.display(display);
+    }
+
+    fn dim(&self) -> u8 {
+        PAUSE_DIM
+    }
+}
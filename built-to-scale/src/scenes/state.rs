@@ -2,30 +2,43 @@ use agb::{
     display::{
         affine::AffineMatrix,
         object::{
-            AffineMatrixInstance, AffineMode, OamIterator, ObjectUnmanaged, Sprite, SpriteLoader,
+            AffineMatrixInstance, AffineMode, GraphicsMode, OamIterator, ObjectUnmanaged, Sprite,
+            SpriteLoader,
         },
-        HEIGHT, WIDTH,
     },
-    fixnum::{num, Vector2D},
-    input::{Button, ButtonController, Tri},
+    fixnum::Vector2D,
+    input::Tri,
     sound::mixer::{Mixer, SoundChannel},
 };
-use util::Number;
+use alloc::boxed::Box;
+use map::Level;
+use physics::{Ghost, Recording};
+use util::{Buttons, InputSnapshot, Number, SaveGame, ScreenSpace, SAVE_SLOTS};
+
+use super::Scene;
 
 pub struct Update<'a, 'b> {
-    button: &'a ButtonController,
+    input: InputSnapshot,
     new_pos: Option<Vector2D<i32>>,
     mixer: &'a mut Mixer<'b>,
     play_space_music: bool,
+    new_level: Option<&'static Level>,
+    saved_game: Option<(usize, SaveGame)>,
+    saved_replay: Option<Recording>,
+    saved_ghost: Option<Ghost>,
 }
 
 impl<'a, 'b> Update<'a, 'b> {
-    pub fn new(button: &'a ButtonController, mixer: &'a mut Mixer<'b>) -> Self {
+    pub fn new(input: InputSnapshot, mixer: &'a mut Mixer<'b>) -> Self {
         Self {
-            button,
+            input,
             new_pos: None,
             mixer,
             play_space_music: false,
+            new_level: None,
+            saved_game: None,
+            saved_replay: None,
+            saved_ghost: None,
         }
     }
 
@@ -44,23 +57,74 @@ impl<'a, 'b> Update<'a, 'b> {
     pub fn should_play_space_music(&self) -> bool {
         self.play_space_music
     }
+
+    pub fn change_level(&mut self, level: &'static Level) {
+        self.new_level = Some(level);
+    }
+
+    pub fn new_level(&self) -> Option<&'static Level> {
+        self.new_level
+    }
+
+    pub fn save_game(&mut self, slot: usize, game: SaveGame) {
+        self.saved_game = Some((slot, game));
+    }
+
+    /// The game to write to save memory, along with the slot it goes in
+    pub fn saved_game(&self) -> Option<(usize, SaveGame)> {
+        self.saved_game
+    }
+
+    pub fn save_replay(&mut self, replay: Recording) {
+        self.saved_replay = Some(replay);
+    }
+
+    /// The replay to write to save memory
+    pub fn take_saved_replay(&mut self) -> Option<Recording> {
+        self.saved_replay.take()
+    }
+
+    pub fn save_ghost(&mut self, ghost: Ghost) {
+        self.saved_ghost = Some(ghost);
+    }
+
+    /// The new best time attack run to write to save memory
+    pub fn saved_ghost(&self) -> Option<&Ghost> {
+        self.saved_ghost.as_ref()
+    }
 }
 
 impl Update<'_, '_> {
-    pub fn button_x_tri(&self) -> Tri {
-        self.button.x_tri()
+    /// The buttons for this frame, wherever they came from
+    pub fn input(&self) -> &InputSnapshot {
+        &self.input
+    }
+
+    pub fn pause_just_pressed(&self) -> bool {
+        self.input.is_just_pressed(Buttons::START)
+    }
+
+    pub fn quit_just_pressed(&self) -> bool {
+        self.input.is_just_pressed(Buttons::SELECT)
+    }
+
+    pub fn menu_x_just_pressed(&self) -> Tri {
+        Tri::from((
+            self.input.is_just_pressed(Buttons::LEFT),
+            self.input.is_just_pressed(Buttons::RIGHT),
+        ))
     }
 
-    pub fn jump_just_pressed(&self) -> bool {
-        self.button.is_just_pressed(Button::A)
+    pub fn replay_just_pressed(&self) -> bool {
+        self.input.is_just_pressed(Buttons::SELECT)
     }
 
-    pub fn jump_pressed(&self) -> bool {
-        self.button.is_pressed(Button::A)
+    pub fn time_attack_just_pressed(&self) -> bool {
+        self.input.is_just_pressed(Buttons::R)
     }
 
-    pub fn is_dash_pressed(&self) -> bool {
-        self.button.is_just_pressed(Button::B)
+    pub fn confirm_just_pressed(&self) -> bool {
+        self.input.is_just_pressed(Buttons::START) || self.input.is_just_pressed(Buttons::A)
     }
 
     pub fn play_sfx(&mut self, effect: &'static [u8]) {
@@ -128,25 +192,77 @@ impl<'a, 'b> Display<'a, 'b> {
         &mut self,
         sprite: &'static Sprite,
         affine: &AffineMatrix,
-        position: Vector2D<Number>,
+        position: ScreenSpace,
+        hflip: bool,
+    ) {
+        let object = self.affine_object(sprite, *affine, position.0, hflip);
+        self.oam_iter.set_next(&object);
+    }
+
+    /// Like [`display`](Self::display), but blended with whatever is behind it
+    pub fn display_translucent(
+        &mut self,
+        sprite: &'static Sprite,
+        affine: &AffineMatrix,
+        position: ScreenSpace,
         hflip: bool,
     ) {
-        let object = self.affine_object(
-            sprite,
-            *affine,
-            position + (num!(0.5), num!(0.5)).into() + (WIDTH / 2, HEIGHT / 2).into(),
-            hflip,
-        );
+        let mut object = self.affine_object(sprite, *affine, position.0, hflip);
+        object.set_graphics_mode(GraphicsMode::AlphaBlending);
         self.oam_iter.set_next(&object);
     }
 
-    pub fn display_regular(&mut self, sprite: &'static Sprite, position: Vector2D<Number>) {
-        let object = self.regular_object(
-            sprite,
-            position + (num!(0.5), num!(0.5)).into() + (WIDTH / 2, HEIGHT / 2).into(),
-        );
+    pub fn display_regular(&mut self, sprite: &'static Sprite, position: ScreenSpace) {
+        let object = self.regular_object(sprite, position.0);
         self.oam_iter.set_next(&object);
     }
 }
 
-pub struct Transition {}
+/// What is handed from one scene to the next when switching scenes
+pub struct Transition {
+    /// The level which the next game is played on
+    pub level: &'static Level,
+    /// The level new games start on
+    pub first_level: &'static Level,
+    /// The save slot the game is played in
+    pub slot: usize,
+    /// What is currently saved in each slot
+    pub saves: [Option<SaveGame>; SAVE_SLOTS],
+    /// The save the next game carries on from, rather than starting the level afresh
+    pub loaded: Option<SaveGame>,
+    /// The last replay saved, which can be watched from the title
+    pub replay: Option<Recording>,
+    /// The fastest time attack run, which is raced against as a ghost
+    pub ghost: Option<Ghost>,
+    /// The scene underneath the pause menu, which carries on when the game is resumed
+    pub(super) paused: Option<Box<dyn Scene>>,
+}
+
+impl Transition {
+    pub fn new(
+        level: &'static Level,
+        saves: [Option<SaveGame>; SAVE_SLOTS],
+        replay: Option<Recording>,
+        ghost: Option<Ghost>,
+    ) -> Self {
+        Self {
+            level,
+            first_level: level,
+            slot: 0,
+            saves,
+            loaded: None,
+            replay,
+            ghost,
+            paused: None,
+        }
+    }
+}
+
+/// How dark the screen should be
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Fade {
+    /// Sixteenths of the way to black
+    pub amount: u8,
+    /// Whether sprites are darkened as well as the backgrounds
+    pub sprites: bool,
+}
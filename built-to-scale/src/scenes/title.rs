@@ -0,0 +1,136 @@
+use agb::{
+    display::{HEIGHT, WIDTH},
+    input::Tri,
+};
+use alloc::{format, string::String};
+use map::Level;
+use util::{CameraTransform, RealSpace, SaveGame, SAVE_SLOTS};
+
+use super::{
+    menu_text::{format_time, MenuText},
+    Display, Scene, Transition, TransitionScene, Update,
+};
+
+/// Shown over the start of the level until a save slot is picked to play in
+pub struct Title {
+    level: &'static Level,
+    saves: [Option<SaveGame>; SAVE_SLOTS],
+    slot: usize,
+    title: MenuText,
+    prompt: MenuText,
+    /// The other ways to play
+    options: MenuText,
+    has_replay: bool,
+    start_pressed: bool,
+    replay_pressed: bool,
+    time_attack_pressed: bool,
+}
+
+impl Title {
+    pub fn new(transition: &Transition) -> Self {
+        let saves = transition.saves;
+        let has_replay = transition.replay.is_some();
+
+        let mut options = String::from("R: time attack");
+        if let Some(ghost) = transition.ghost.as_ref() {
+            options += &format!(", best {}", format_time(ghost.frames()));
+        }
+        if has_replay {
+            options += "\nSelect: watch replay";
+        }
+
+        Self {
+            level: transition.level,
+            saves,
+            slot: 0,
+            title: MenuText::new("Built to Scale", HEIGHT / 4),
+            prompt: slot_text(0, saves[0].as_ref()),
+            options: MenuText::new(&options, HEIGHT - 28),
+            has_replay,
+            start_pressed: false,
+            replay_pressed: false,
+            time_attack_pressed: false,
+        }
+    }
+}
+
+/// Describes what is in the slot
+fn slot_text(slot: usize, save: Option<&SaveGame>) -> MenuText {
+    let contents = match save {
+        Some(save) => {
+            let seconds = save.play_time / 60;
+            format!("{}:{:02} played", seconds / 60, seconds % 60)
+        }
+        None => "New game".into(),
+    };
+
+    MenuText::new(
+        &format!("< Slot {} >\n{contents}", slot + 1),
+        HEIGHT * 2 / 3,
+    )
+}
+
+impl Scene for Title {
+    fn transition(&mut self, transition: &mut Transition) -> Option<TransitionScene> {
+        if self.replay_pressed {
+            let level = transition
+                .replay
+                .as_ref()
+                .and_then(|replay| map::LEVELS.get(replay.start.level as usize).copied());
+
+            if let Some(level) = level {
+                transition.level = level;
+                return Some(TransitionScene::Replay);
+            }
+        }
+
+        if self.time_attack_pressed {
+            transition.level = transition.first_level;
+            return Some(TransitionScene::TimeAttack);
+        }
+
+        if !self.start_pressed {
+            return None;
+        }
+
+        let save = self.saves[self.slot];
+        let saved_level = save.and_then(|save| map::LEVELS.get(save.level as usize).copied());
+
+        transition.slot = self.slot;
+        // saves from levels which no longer exist start a new game instead
+        transition.loaded = save.filter(|_| saved_level.is_some());
+        transition.level = saved_level.unwrap_or(transition.first_level);
+
+        Some(TransitionScene::Game)
+    }
+
+    fn update(&mut self, update: &mut Update) {
+        let camera =
+            CameraTransform::new(RealSpace(self.level.camera_start), (WIDTH, HEIGHT).into());
+        update.set_pos(camera.top_left());
+
+        let slot = match update.menu_x_just_pressed() {
+            Tri::Negative => (self.slot + SAVE_SLOTS - 1) % SAVE_SLOTS,
+            Tri::Zero => self.slot,
+            Tri::Positive => (self.slot + 1) % SAVE_SLOTS,
+        };
+        if slot != self.slot {
+            self.slot = slot;
+            self.prompt = slot_text(slot, self.saves[slot].as_ref());
+        }
+
+        self.title.update();
+        self.prompt.update();
+        self.options.update();
+
+        self.start_pressed = update.confirm_just_pressed();
+        self.replay_pressed = self.has_replay && update.replay_just_pressed();
+        self.time_attack_pressed = update.time_attack_just_pressed();
+    }
+
+    fn display(&mut self, display: &mut Display) {
+        self.title.display(display);
+        self.prompt.display(display);
+        self.options.display(display);
+    }
+}
@@ -0,0 +1,236 @@
+use std::collections::HashMap;
+
+use proc_macro2::TokenStream;
+use quote::quote;
+use tiled::ObjectShape;
+use util::Number;
+
+use crate::{
+    collider_extract::quote_vec,
+    properties,
+    world::{level_objects, MapPart},
+};
+
+const SCREEN_WIDTH: f32 = 240.;
+const SCREEN_HEIGHT: f32 = 160.;
+
+const CAMERA_ZONE_BOX_SIZE: i32 = 128;
+
+/// How the camera is held while the player is in a zone, set by the zone's `camera` property
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum ZoneKind {
+    /// The camera can move around, but never shows anything outside of the zone
+    Clamp,
+    /// The camera is held on the vertical centre line of the zone, and clamped along it
+    PinX,
+    /// The camera is held on the horizontal centre line of the zone, and clamped along it
+    PinY,
+    /// The camera stays at the centre of the zone
+    Fixed,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct CameraZone {
+    id: u32,
+    min: (f32, f32),
+    max: (f32, f32),
+    kind: ZoneKind,
+}
+
+impl CameraZone {
+    fn overlaps(&self, other: &CameraZone) -> bool {
+        self.min.0 < other.max.0
+            && other.min.0 < self.max.0
+            && self.min.1 < other.max.1
+            && other.min.1 < self.max.1
+    }
+
+    /// The range the centre of the camera is allowed to be in, so that the screen stays inside
+    /// the zone. Zones smaller than the screen keep the camera at their centre.
+    fn camera_range(&self) -> ((f32, f32), (f32, f32)) {
+        let axis = |min: f32, max: f32, half_screen: f32, pinned: bool| {
+            if pinned || max - min <= half_screen * 2. {
+                let centre = (min + max) / 2.;
+                (centre, centre)
+            } else {
+                (min + half_screen, max - half_screen)
+            }
+        };
+
+        let (min_x, max_x) = axis(
+            self.min.0,
+            self.max.0,
+            SCREEN_WIDTH / 2.,
+            matches!(self.kind, ZoneKind::PinX | ZoneKind::Fixed),
+        );
+        let (min_y, max_y) = axis(
+            self.min.1,
+            self.max.1,
+            SCREEN_HEIGHT / 2.,
+            matches!(self.kind, ZoneKind::PinY | ZoneKind::Fixed),
+        );
+
+        ((min_x, min_y), (max_x, max_y))
+    }
+}
+
+fn extract_camera_zones(parts: &[MapPart]) -> Vec<CameraZone> {
+    level_objects(parts, "Camera zones")
+        .into_iter()
+        .map(|(object, offset)| {
+            let ObjectShape::Rect { width, height } = object.shape else {
+                panic!("Camera zone {} should be a rectangle", object.id());
+            };
+            assert!(
+                width > 0. && height > 0.,
+                "Camera zone {} should have a size",
+                object.id()
+            );
+
+            let kind = match properties::get_string(&object.properties, "camera") {
+                None | Some("clamp") => ZoneKind::Clamp,
+                Some("pin_x") => ZoneKind::PinX,
+                Some("pin_y") => ZoneKind::PinY,
+                Some("fixed") => ZoneKind::Fixed,
+                Some(kind) => panic!(
+                    "Camera zone {} should be clamp, pin_x, pin_y or fixed, not {kind}",
+                    object.id()
+                ),
+            };
+
+            let x = object.x + offset.x;
+            let y = object.y + offset.y;
+
+            CameraZone {
+                id: object.id(),
+                min: (x, y),
+                max: (x + width, y + height),
+                kind,
+            }
+        })
+        .collect()
+}
+
+/// Zones which overlap would each want the camera somewhere different when the player is in both
+fn check_conflicts(zones: &[CameraZone]) {
+    for (idx, zone) in zones.iter().enumerate() {
+        for other in &zones[idx + 1..] {
+            assert!(
+                !zone.overlaps(other),
+                "Camera zones {} and {} overlap, so the camera can't be in both",
+                zone.id,
+                other.id
+            );
+        }
+    }
+}
+
+pub fn get_camera_zones(parts: &[MapPart]) -> String {
+    let zones = extract_camera_zones(parts);
+    check_conflicts(&zones);
+
+    let mut boxes: HashMap<(i32, i32), Vec<usize>> = HashMap::new();
+    for (idx, zone) in zones.iter().enumerate() {
+        let min_x = (zone.min.0.floor() as i32).div_floor(CAMERA_ZONE_BOX_SIZE);
+        let min_y = (zone.min.1.floor() as i32).div_floor(CAMERA_ZONE_BOX_SIZE);
+        let max_x = (zone.max.0.ceil() as i32).div_floor(CAMERA_ZONE_BOX_SIZE);
+        let max_y = (zone.max.1.ceil() as i32).div_floor(CAMERA_ZONE_BOX_SIZE);
+
+        for y in min_y..=max_y {
+            for x in min_x..=max_x {
+                boxes.entry((x, y)).or_default().push(idx);
+            }
+        }
+    }
+
+    let quoted_zones = zones.iter().map(|zone| {
+        let (camera_min, camera_max) = zone.camera_range();
+        let vec = |(x, y): (f32, f32)| quote_vec((Number::from_f32(x), Number::from_f32(y)).into());
+
+        let min = vec(zone.min);
+        let max = vec(zone.max);
+        let camera_min = vec(camera_min);
+        let camera_max = vec(camera_max);
+
+        quote! {
+            super::CameraZone {
+                min: #min,
+                max: #max,
+                camera_min: #camera_min,
+                camera_max: #camera_max,
+            }
+        }
+    });
+
+    let mut phf = phf_codegen::Map::new();
+    for ((x, y), zones) in boxes {
+        let zones = zones.iter().map(|idx| quote! { &CAMERA_ZONE_LIST[#idx] });
+        phf.entry([x, y], &quote! { &[#(#zones),*] }.to_string());
+    }
+
+    format!(
+        "{}{};",
+        quote! {
+            // only used by the lookup, which is empty in levels with no zones
+            #[allow(dead_code)]
+            static CAMERA_ZONE_LIST: &[super::CameraZone] = &[#(#quoted_zones),*];
+
+            pub static CAMERA_ZONES: phf::Map<[i32; 2], &'static [&'static super::CameraZone]> =
+        },
+        phf.build()
+    )
+}
+
+pub fn quote_box_size() -> TokenStream {
+    quote! {
+        pub const CAMERA_ZONE_BOX: i32 = #CAMERA_ZONE_BOX_SIZE;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn zone(min: (f32, f32), max: (f32, f32), kind: ZoneKind) -> CameraZone {
+        CameraZone {
+            id: 0,
+            min,
+            max,
+            kind,
+        }
+    }
+
+    #[test]
+    fn clamp_keeps_the_screen_inside_the_zone() {
+        let zone = zone((0., 0.), (480., 320.), ZoneKind::Clamp);
+
+        assert_eq!(zone.camera_range(), ((120., 80.), (360., 240.)));
+    }
+
+    #[test]
+    fn zones_smaller_than_the_screen_centre_the_camera() {
+        let zone = zone((0., 0.), (100., 320.), ZoneKind::Clamp);
+
+        assert_eq!(zone.camera_range(), ((50., 80.), (50., 240.)));
+    }
+
+    #[test]
+    fn pinned_and_fixed_zones_hold_the_camera_on_their_centre() {
+        let pinned = zone((0., 0.), (480., 320.), ZoneKind::PinY);
+        assert_eq!(pinned.camera_range(), ((120., 160.), (360., 160.)));
+
+        let fixed = zone((0., 0.), (480., 320.), ZoneKind::Fixed);
+        assert_eq!(fixed.camera_range(), ((240., 160.), (240., 160.)));
+    }
+
+    #[test]
+    fn touching_zones_do_not_overlap() {
+        let left = zone((0., 0.), (100., 100.), ZoneKind::Clamp);
+        let right = zone((100., 0.), (200., 100.), ZoneKind::Fixed);
+        let middle = zone((50., 50.), (150., 150.), ZoneKind::Clamp);
+
+        assert!(!left.overlaps(&right));
+        assert!(left.overlaps(&middle));
+        assert!(right.overlaps(&middle));
+    }
+}
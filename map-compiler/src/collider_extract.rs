@@ -4,15 +4,20 @@ use std::{
 };
 
 use agb_fixnum::{Num, Vector2D};
-use itertools::Itertools;
 use nalgebra::{Vector2, Vector3};
 use proc_macro2::TokenStream;
-use tiled::{Map, Object, ObjectShape, PropertyValue};
+use tiled::{Object, ObjectShape};
 use util::{Arc, Circle, Collider, ColliderKind, ColliderTag, Line, Number};
 
 use quote::{format_ident, quote};
 
-use crate::spiral::{perimeter, SpiralIterator};
+use crate::{
+    properties,
+    simplify::{self, simplify},
+    spiral::{perimeter, SpiralIterator},
+    tile_collider_extract::extract_tile_outlines,
+    world::{level_objects, MapPart},
+};
 
 /// These control the performance and ROM size
 /// The size of a box of colliders in pixels
@@ -103,19 +108,45 @@ struct ColliderGroup {
     name: String,
     class: String,
     colliders: Vec<Collider>,
+    /// The centre of the object's bounding box, used to position its image
+    center: Vector2D<Number>,
+    /// How far along its path this object starts, relative to the path itself
+    phase: f64,
+}
+
+fn object_center(object: &Object) -> Vector2D<Number> {
+    let (min, max) = match &object.shape {
+        tiled::ObjectShape::Rect { width, height }
+        | tiled::ObjectShape::Ellipse { width, height } => ((0., 0.), (*width, *height)),
+        tiled::ObjectShape::Polygon { points } | tiled::ObjectShape::Polyline { points } => {
+            points.iter().fold(
+                ((f32::MAX, f32::MAX), (f32::MIN, f32::MIN)),
+                |(min, max), &(x, y)| ((min.0.min(x), min.1.min(y)), (max.0.max(x), max.1.max(y))),
+            )
+        }
+        _ => ((0., 0.), (0., 0.)),
+    };
+
+    (
+        Number::from_f32(object.x + (min.0 + max.0) / 2.),
+        Number::from_f32(object.y + (min.1 + max.1) / 2.),
+    )
+        .into()
 }
 
 fn extract_from_layer<'a>(
-    layer: impl Iterator<Item = Object<'a>>,
+    layer: impl Iterator<Item = (Object<'a>, Vector2<f32>)>,
     tag: ColliderTag,
 ) -> Vec<ColliderGroup> {
     let mut all_colliders = Vec::new();
-    for object in layer {
+    for (object, offset) in layer {
+        let origin = Vector2::new(object.x, object.y) + offset;
         let mut colliders = Vec::new();
         match &object.shape {
             tiled::ObjectShape::Rect { width, height } => {
                 handle_points_for_collider(
-                    object,
+                    origin,
+                    object_corner_style(&object),
                     &[(0., 0.), (*width, 0.), (*width, *height), (0., *height)],
                     &mut colliders,
                     tag,
@@ -129,8 +160,8 @@ fn extract_from_layer<'a>(
                 );
 
                 let position = (
-                    Number::from_f32(object.x + *width / 2.),
-                    Number::from_f32(object.y + *width / 2.),
+                    Number::from_f32(origin.x + *width / 2.),
+                    Number::from_f32(origin.y + *width / 2.),
                 )
                     .into();
 
@@ -145,7 +176,8 @@ fn extract_from_layer<'a>(
             }
             tiled::ObjectShape::Polygon { points } | tiled::ObjectShape::Polyline { points } => {
                 handle_points_for_collider(
-                    object,
+                    origin,
+                    object_corner_style(&object),
                     points,
                     &mut colliders,
                     tag,
@@ -159,32 +191,75 @@ fn extract_from_layer<'a>(
             name: object.name.clone(),
             class: object.user_type.clone(),
             colliders,
+            center: object_center(&object) + to_vec(offset),
+            phase: properties::get_float(&object.properties, "phase").unwrap_or(0.) as f64,
         })
     }
 
     all_colliders
 }
 
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum CornerKind {
+    Round,
+    Sharp,
+    Chamfer,
+}
+
+/// How the corners of a polygon or polyline are turned into colliders, set by the `corner`,
+/// `radius` and `concave_radius` properties of the object
+#[derive(Clone, Copy, Debug)]
+struct CornerStyle {
+    kind: CornerKind,
+    /// The radius of rounded convex corners, or how far along each side a chamfer starts
+    convex_radius: f32,
+    concave_radius: f32,
+}
+
+impl Default for CornerStyle {
+    fn default() -> Self {
+        Self {
+            kind: CornerKind::Round,
+            convex_radius: 2.,
+            concave_radius: 10.,
+        }
+    }
+}
+
+fn object_corner_style(object: &Object) -> CornerStyle {
+    let default = CornerStyle::default();
+
+    let kind = match properties::get_string(&object.properties, "corner") {
+        None | Some("round") => CornerKind::Round,
+        Some("sharp") => CornerKind::Sharp,
+        Some("chamfer") => CornerKind::Chamfer,
+        Some(corner) => {
+            panic!("Unknown corner style '{corner}', should be round, sharp or chamfer")
+        }
+    };
+
+    let convex_radius =
+        properties::get_float(&object.properties, "radius").unwrap_or(default.convex_radius);
+    // concave corners are much larger by default so the player can roll smoothly through them
+    let concave_radius =
+        properties::get_float(&object.properties, "concave_radius").unwrap_or(convex_radius * 5.);
+
+    CornerStyle {
+        kind,
+        convex_radius,
+        concave_radius,
+    }
+}
+
 fn handle_points_for_collider(
-    object: tiled::Object,
+    origin: Vector2<f32>,
+    corner_style: CornerStyle,
     points: &[(f32, f32)],
     colliders: &mut Vec<Collider>,
     tag: ColliderTag,
     is_polygon: bool,
 ) {
-    let origin = Vector2::new(object.x, object.y);
-
-    let radius = object
-        .properties
-        .get("radius")
-        .map(|radius| {
-            if let PropertyValue::FloatValue(radius) = radius {
-                *radius
-            } else {
-                panic!("Invalid value for radius {radius:?}")
-            }
-        })
-        .unwrap_or(2.);
+    let points = &simplify(points, is_polygon)[..];
 
     if points.len() == 2 {
         colliders.extend(get_line_colliders(
@@ -203,7 +278,7 @@ fn handle_points_for_collider(
         let o = Vector2::new(o.0, o.1) + origin;
         let b = Vector2::new(b.0, b.1) + origin;
 
-        modified_points.push(rounded_line_collider(a, o, b, radius, tag, colliders));
+        modified_points.push(corner_collider(a, o, b, corner_style, tag, colliders));
     };
 
     for x in points.windows(3) {
@@ -245,43 +320,55 @@ fn handle_points_for_collider(
     }
 }
 
-fn extract_colliders(map: &Map) -> Vec<ColliderGroup> {
-    let gravitational_objects = map
-        .layers()
-        .filter(|x| x.name == "Colliders")
-        .find_map(|x| x.as_object_layer())
-        .unwrap();
-
-    let non_gravitattional_objects = map
-        .layers()
-        .filter(|x| x.name == "Colliders No Gravity")
-        .find_map(|x| x.as_object_layer())
-        .unwrap();
-
-    let killision = map
-        .layers()
-        .filter(|x| x.name == "Killision")
-        .find_map(|x| x.as_object_layer())
-        .unwrap();
-
+fn extract_colliders(parts: &[MapPart]) -> Vec<ColliderGroup> {
     let mut o = extract_from_layer(
-        gravitational_objects.objects(),
+        level_objects(parts, "Colliders").into_iter(),
         ColliderTag::CollisionGravitational,
     );
     o.extend(extract_from_layer(
-        non_gravitattional_objects.objects(),
+        level_objects(parts, "Colliders No Gravity").into_iter(),
         ColliderTag::CollisionOnly,
     ));
     o.extend(extract_from_layer(
-        killision
-            .objects()
-            .filter(|x| !matches!(x.shape, tiled::ObjectShape::Point(_, _))),
+        level_objects(parts, "Killision")
+            .into_iter()
+            .filter(|(x, _)| !matches!(x.shape, tiled::ObjectShape::Point(_, _))),
         ColliderTag::Killision,
     ));
+    o.extend(extract_from_tiles(parts));
 
     o
 }
 
+fn extract_from_tiles(parts: &[MapPart]) -> Vec<ColliderGroup> {
+    let outlines = extract_tile_outlines(parts);
+
+    outlines
+        .into_iter()
+        .map(|outline| {
+            let points: Vec<_> = outline.points.iter().map(|x| (x.x, x.y)).collect();
+            let mut colliders = Vec::new();
+
+            handle_points_for_collider(
+                Vector2::zeros(),
+                CornerStyle::default(),
+                &points,
+                &mut colliders,
+                outline.tag,
+                outline.is_polygon,
+            );
+
+            ColliderGroup {
+                name: String::new(),
+                class: String::new(),
+                colliders,
+                center: (0, 0).into(),
+                phase: 0.,
+            }
+        })
+        .collect()
+}
+
 fn coordinates_to_generate_box_list_from<T>(
     spacial_colliders: &HashMap<(i32, i32), T>,
 ) -> HashSet<(i32, i32)> {
@@ -376,18 +463,18 @@ fn get_3_and_first_gravity(
     resultant
 }
 
-fn extract_recovery_points(map: &Map) -> Vec<Vector2D<Number>> {
-    let layer = map
-        .layers()
-        .filter(|x| x.name == "Killision")
-        .find_map(|x| x.as_object_layer())
-        .unwrap();
-
-    layer
-        .objects()
-        .filter_map(|x| {
+fn extract_recovery_points(parts: &[MapPart]) -> Vec<Vector2D<Number>> {
+    level_objects(parts, "Killision")
+        .into_iter()
+        .filter_map(|(x, offset)| {
             if let ObjectShape::Point(x, y) = x.shape {
-                Some((Number::from_f32(x), Number::from_f32(y)).into())
+                Some(
+                    (
+                        Number::from_f32(x + offset.x),
+                        Number::from_f32(y + offset.y),
+                    )
+                        .into(),
+                )
             } else {
                 None
             }
@@ -395,7 +482,7 @@ fn extract_recovery_points(map: &Map) -> Vec<Vector2D<Number>> {
         .collect()
 }
 
-fn quote_vec(vector: Vector2D<Number>) -> TokenStream {
+pub fn quote_vec(vector: Vector2D<Number>) -> TokenStream {
     let x = vector.x.to_raw();
     let y = vector.y.to_raw();
 
@@ -408,19 +495,130 @@ struct Path {
     name: String,
     points: Vec<Vector2D<Number>>,
     complete: bool,
-    speed: f64,
+    one_shot: bool,
+    easing: Easing,
+    phase: f64,
+    /// The speed along the segment starting at each point
+    speeds: Vec<f64>,
+    /// The number of frames to wait on arriving at each point
+    pauses: Vec<u16>,
 }
 
-fn extract_paths(map: &Map) -> Vec<Path> {
-    let path_layer = map
-        .layers()
-        .filter(|x| x.name == "Paths")
-        .find_map(|x| x.as_object_layer())
-        .unwrap();
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Easing {
+    Linear,
+    EaseIn,
+    EaseOut,
+    EaseInOut,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct PathStart {
+    index: usize,
+    forwards: bool,
+    timer: f64,
+    pause: u16,
+    /// A one-shot path which has already reached its end
+    finished: bool,
+}
+
+impl Path {
+    fn segment_length(&self, idx: usize) -> f64 {
+        let a = self.points[idx];
+        let b = self.points[(idx + 1) % self.points.len()];
+
+        let length = (b - a).magnitude();
+        length.to_raw() as f64 / (1 << 8) as f64
+    }
+
+    /// The fraction of the segment starting at `idx` which is covered each frame
+    fn segment_incrementer(&self, idx: usize) -> f64 {
+        self.speeds[idx] / self.segment_length(idx)
+    }
+
+    /// The (from, to, forwards) segments the path goes through in one full cycle
+    fn legs(&self) -> Vec<(usize, usize, bool)> {
+        let len = self.points.len();
+
+        if self.complete {
+            (0..len).map(|idx| (idx, (idx + 1) % len, true)).collect()
+        } else if self.one_shot {
+            (0..len - 1).map(|idx| (idx, idx + 1, true)).collect()
+        } else {
+            (0..len - 1)
+                .map(|idx| (idx, idx + 1, true))
+                .chain((1..len).rev().map(|idx| (idx, idx - 1, false)))
+                .collect()
+        }
+    }
+
+    /// Works out where along the path a body should start given the `phase` of the path
+    /// and the additional phase offset of the body itself
+    fn start(&self, phase_offset: f64) -> PathStart {
+        let legs = self.legs();
+        let len = self.points.len();
+
+        let leg_frames = |(from, to, forwards): (usize, usize, bool)| {
+            1. / self.segment_incrementer(if forwards { from } else { to })
+        };
+
+        let total_frames: f64 = legs
+            .iter()
+            .map(|&leg| leg_frames(leg) + self.pauses[leg.1] as f64)
+            .sum();
+
+        let mut remaining = (self.phase + phase_offset).rem_euclid(1.) * total_frames;
+
+        for (from, to, forwards) in legs {
+            let frames = leg_frames((from, to, forwards));
+            if remaining < frames {
+                return PathStart {
+                    index: from,
+                    forwards,
+                    timer: remaining / frames,
+                    pause: 0,
+                    finished: false,
+                };
+            }
+            remaining -= frames;
+
+            let pause = self.pauses[to] as f64;
+            if remaining < pause {
+                // one-shot paths stay where they end rather than going round again
+                let end = if self.complete { 0 } else { len - 1 };
+                let finished = self.one_shot && forwards && to == end;
+
+                let forwards = if forwards {
+                    finished || self.complete || to != len - 1
+                } else {
+                    to == 0
+                };
+
+                return PathStart {
+                    index: to,
+                    forwards,
+                    timer: 0.,
+                    pause: (pause - remaining) as u16,
+                    finished,
+                };
+            }
+            remaining -= pause;
+        }
+
+        PathStart {
+            index: 0,
+            forwards: true,
+            timer: 0.,
+            pause: 0,
+            finished: false,
+        }
+    }
+}
 
-    path_layer
-        .objects()
-        .map(|object| {
+fn extract_paths(parts: &[MapPart]) -> Vec<Path> {
+    level_objects(parts, "Paths")
+        .into_iter()
+        .map(|(object, offset)| {
             let is_complete = matches!(object.shape, ObjectShape::Polygon { .. });
 
             let points = match &object.shape {
@@ -429,14 +627,35 @@ fn extract_paths(map: &Map) -> Vec<Path> {
                 _ => panic!("Path should be polyline or polygon"),
             };
 
-            let speed = object
-                .properties
-                .get("speed")
-                .map(|x| match x {
-                    PropertyValue::FloatValue(x) => *x as f64,
-                    _ => panic!("Speed should be a float"),
-                })
+            let speed = properties::get_float(&object.properties, "speed")
                 .expect("Moving path should specify a speed");
+            let pause = properties::get_int(&object.properties, "pause").unwrap_or(0);
+
+            // individual segments and points can override the speed and pause of the path
+            let speeds = (0..points.len())
+                .map(|idx| {
+                    properties::get_float(&object.properties, &format!("speed_{idx}"))
+                        .unwrap_or(speed) as f64
+                })
+                .collect();
+            let pauses = (0..points.len())
+                .map(|idx| {
+                    let pause = properties::get_int(&object.properties, &format!("pause_{idx}"))
+                        .unwrap_or(pause);
+                    u16::try_from(pause).expect("Pause should be a positive number of frames")
+                })
+                .collect();
+
+            let easing = match properties::get_string(&object.properties, "easing") {
+                None | Some("linear") => Easing::Linear,
+                Some("ease_in") => Easing::EaseIn,
+                Some("ease_out") => Easing::EaseOut,
+                Some("ease_in_out") => Easing::EaseInOut,
+                Some(easing) => panic!("Unknown easing {easing} for path {}", object.name),
+            };
+
+            let one_shot = properties::get_bool(&object.properties, "one_shot").unwrap_or(false);
+            let phase = properties::get_float(&object.properties, "phase").unwrap_or(0.) as f64;
 
             Path {
                 name: object.name.clone(),
@@ -445,87 +664,156 @@ fn extract_paths(map: &Map) -> Vec<Path> {
                     .copied()
                     .map(|(x, y)| {
                         (
-                            Number::from_f32(x + object.x),
-                            Number::from_f32(y + object.y),
+                            Number::from_f32(x + object.x + offset.x),
+                            Number::from_f32(y + object.y + offset.y),
                         )
                             .into()
                     })
                     .collect(),
                 complete: is_complete,
-                speed,
+                one_shot,
+                easing,
+                phase,
+                speeds,
+                pauses,
             }
         })
         .collect()
 }
 
-fn assemble_dynamic_colliders(map: &Map) -> String {
-    let dynamic_colliders: Vec<_> = extract_colliders(map)
+/// Objects which share a path and a phase move as one rigid body
+struct Body<'a> {
+    phase: f64,
+    groups: Vec<&'a ColliderGroup>,
+}
+
+/// The names of the images of the objects which move along paths
+pub fn dynamic_collider_images(parts: &[MapPart]) -> Vec<String> {
+    extract_colliders(parts)
+        .into_iter()
+        .filter(|x| !x.name.is_empty())
+        .map(|x| x.name)
+        .collect()
+}
+
+/// The sizes of the boxes colliders and paths are looked up in, which are the same for every level
+pub fn quote_box_sizes() -> TokenStream {
+    quote! {
+        pub const BOX_SIZE: i32 = #BOX_SIZE;
+        pub const PATH_BOX_SIZE: i32 = #PATH_BOX_SIZE;
+    }
+}
+
+fn assemble_dynamic_colliders(parts: &[MapPart]) -> String {
+    let dynamic_colliders: Vec<_> = extract_colliders(parts)
         .iter()
         .filter(|&x| !x.name.is_empty())
         .cloned()
         .collect();
 
-    let dynamic_object_images = dynamic_colliders
-        .iter()
-        .map(|x| x.name.as_str())
-        .unique()
-        .map(|x| format_ident!("{}", x));
+    let paths = extract_paths(parts);
 
-    let paths = extract_paths(map);
+    for collider_group in dynamic_colliders.iter() {
+        assert!(
+            paths.iter().any(|x| x.name == collider_group.class),
+            "Should be a path for an object, {} wants {}",
+            collider_group.name,
+            collider_group.class
+        );
+    }
 
-    // lookup what index the collider group is stored in
-    let collider_group_indexes: HashMap<&str, usize> = dynamic_colliders
+    let path_bodies: Vec<Vec<Body>> = paths
         .iter()
-        .enumerate()
-        .map(|(idx, x)| (x.class.as_str(), idx))
-        .collect();
+        .map(|path| {
+            let mut bodies: Vec<Body> = Vec::new();
+
+            for collider_group in dynamic_colliders.iter().filter(|x| x.class == path.name) {
+                match bodies.iter_mut().find(|x| x.phase == collider_group.phase) {
+                    Some(body) => body.groups.push(collider_group),
+                    None => bodies.push(Body {
+                        phase: collider_group.phase,
+                        groups: vec![collider_group],
+                    }),
+                }
+            }
 
-    let dynamic_collider_groups = dynamic_colliders.iter().map(|collider_group| {
-        let path = paths
-            .iter()
-            .find(|x| x.name == collider_group.class)
-            .expect("Should be a path for an object");
+            assert!(
+                !bodies.is_empty(),
+                "Find object group for path {}",
+                path.name
+            );
 
-        let path_points = path
-            .points
-            .iter()
-            .copied()
-            .chain(core::iter::once(path.points[0]))
-            .tuple_windows()
-            .map(|(a, b)| {
-                let length = (b - a).magnitude();
-                let length = length.to_raw() as f64 / (1 << 8) as f64;
-                let time = path.speed * 1. / length;
-                let time_fixed = Num::<i32, 24>::from_f64(time);
-
-                (a, time_fixed)
-            });
-        let points = path_points.map(|(point, frames)| {
+            bodies
+        })
+        .collect();
+
+    let quoted_paths = paths.iter().zip(&path_bodies).map(|(path, bodies)| {
+        let points = path.points.iter().enumerate().map(|(idx, &point)| {
             let point = quote_vec(point);
-            let incrementer = frames.to_raw();
+            let incrementer = Num::<i32, 24>::from_f64(path.segment_incrementer(idx)).to_raw();
+            let pause = path.pauses[idx];
 
             quote! {
                 PathPoint {
                     point: #point,
                     incrementer: Num::from_raw(#incrementer),
+                    pause: #pause,
+                }
+            }
+        });
+
+        let bodies = bodies.iter().map(|body| {
+            let colliders = body
+                .groups
+                .iter()
+                .flat_map(|x| x.colliders.iter())
+                .map(quote_collider);
+            let images = body.groups.iter().map(|x| {
+                let image = format_ident!("{}", x.name);
+                let offset = quote_vec(x.center - path.points[0]);
+
+                quote! {
+                    BodyImage {
+                        image: DynamicColliderImage::#image,
+                        offset: #offset,
+                    }
+                }
+            });
+            let start = quote_path_start(path.start(body.phase));
+
+            quote! {
+                Body {
+                    colliders: &[
+                        #(#colliders),*
+                    ],
+                    images: &[
+                        #(#images),*
+                    ],
+                    start: #start,
                 }
             }
         });
 
-        let colliders = collider_group.colliders.iter().map(quote_collider);
         let complete = path.complete;
-        let image = format_ident!("{}", collider_group.name);
+        let one_shot = path.one_shot;
+        let easing = match path.easing {
+            Easing::Linear => quote! { PathEasing::Linear },
+            Easing::EaseIn => quote! { PathEasing::EaseIn },
+            Easing::EaseOut => quote! { PathEasing::EaseOut },
+            Easing::EaseInOut => quote! { PathEasing::EaseInOut },
+        };
 
         quote! {
             Path{
                 points: &[
                     #(#points),*
                 ],
-                colliders:  &[
-                    #(#colliders),*
+                bodies: &[
+                    #(#bodies),*
                 ],
                 complete: #complete,
-                image: DynamicColliderImage::#image,
+                one_shot: #one_shot,
+                easing: #easing,
             }
         }
     });
@@ -534,12 +822,7 @@ fn assemble_dynamic_colliders(map: &Map) -> String {
 
     let mut boxes_path_crosses_idx: HashMap<(i32, i32), Vec<usize>> = HashMap::new();
 
-    for path in paths.iter() {
-        // find out which group this path is for
-        let collider_group_idx = collider_group_indexes
-            .get(path.name.as_str())
-            .copied()
-            .expect("Find object group for path");
+    for (path_idx, path) in paths.iter().enumerate() {
         let boxes_path_goes_through: HashSet<(i32, i32)> = path
             .points
             .windows(2)
@@ -560,14 +843,14 @@ fn assemble_dynamic_colliders(map: &Map) -> String {
             boxes_path_crosses_idx
                 .entry((x, y))
                 .or_default()
-                .push(collider_group_idx);
+                .push(path_idx);
         }
     }
 
     for ((x, y), path_idx) in boxes_path_crosses_idx {
         let references = path_idx.into_iter().map(|idx| {
             quote! {
-                &DYNAMIC_COLLIDER_GROUPS[#idx]
+                &PATHS[#idx]
             }
         });
         phf.entry([x, y], &format!("{}", quote! { &[ #(#references),* ] }));
@@ -577,17 +860,10 @@ fn assemble_dynamic_colliders(map: &Map) -> String {
         "{}{};\n\n",
         quote! {
 
-            pub static DYNAMIC_COLLIDER_GROUPS: &[Path] = &[
-                #(#dynamic_collider_groups),*
+            pub static PATHS: &[Path] = &[
+                #(#quoted_paths),*
             ];
 
-            #[derive(Clone, Copy)]
-            pub enum DynamicColliderImage {
-                #(#dynamic_object_images),*
-            }
-
-            pub const PATH_BOX_SIZE: i32 = #PATH_BOX_SIZE;
-
             pub static PATH_LOOKUP: phf::Map<[i32; 2], &'static [&'static Path]> =
 
         },
@@ -595,6 +871,28 @@ fn assemble_dynamic_colliders(map: &Map) -> String {
     )
 }
 
+fn quote_path_start(start: PathStart) -> TokenStream {
+    let index = start.index;
+    let direction = if start.forwards {
+        quote! { PathDirection::Forwards }
+    } else {
+        quote! { PathDirection::Backwards }
+    };
+    let timer = Num::<i32, 24>::from_f64(start.timer).to_raw();
+    let pause = start.pause;
+    let finished = start.finished;
+
+    quote! {
+        PathStart {
+            index: #index,
+            direction: #direction,
+            timer: Num::from_raw(#timer),
+            pause: #pause,
+            finished: #finished,
+        }
+    }
+}
+
 fn quote_collider(collider: &Collider) -> TokenStream {
     let kind = match &collider.kind {
         ColliderKind::Circle(c) => {
@@ -666,8 +964,8 @@ fn quote_collider(collider: &Collider) -> TokenStream {
     }
 }
 
-pub fn assemble_colliders(map: &Map) -> String {
-    let colliders: Vec<_> = extract_colliders(map)
+pub fn assemble_colliders(parts: &[MapPart]) -> String {
+    let colliders: Vec<_> = extract_colliders(parts)
         .iter()
         .filter(|&x| x.name.is_empty())
         .cloned()
@@ -699,14 +997,12 @@ pub fn assemble_colliders(map: &Map) -> String {
 
     let collider_phf_code = collider_phf.build();
 
-    let recovery_points = extract_recovery_points(map);
+    let recovery_points = extract_recovery_points(parts);
     let recovery_points = recovery_points.into_iter().map(quote_vec);
 
     format!(
         "{}{};\n\n{}",
         quote! {
-            pub const BOX_SIZE: i32 = #BOX_SIZE;
-
             static COLLIDERS: &[Collider] = &[#(#colliders_quote),*];
 
             pub static RECOVERY_POINTS: &[Vector2D<Number>] = &[
@@ -716,16 +1012,29 @@ pub fn assemble_colliders(map: &Map) -> String {
             pub static NEARBY_COLLIDERS: phf::Map<[i32; 2], &'static [&'static Collider]> =
         },
         collider_phf_code,
-        assemble_dynamic_colliders(map),
+        assemble_dynamic_colliders(parts),
     )
 }
 
-// pushes the circle that should be added, and returns the replacement end / start positions (so where line ao and ob should actually finish)
-fn rounded_line_collider(
+/// A collider with no size at a sharp convex corner, so that the player is pushed away from the
+/// corner itself rather than along the normal of either line
+fn point_collider(point: Vector2<f32>, tag: ColliderTag) -> Collider {
+    Collider {
+        kind: ColliderKind::Circle(Circle {
+            position: to_vec(point),
+            radius: 0.into(),
+        }),
+        velocity: Vector2D::new(0.into(), 0.into()),
+        tag,
+    }
+}
+
+// pushes the colliders for the corner, and returns the replacement end / start positions (so where line ao and ob should actually finish)
+fn corner_collider(
     a: Vector2<f32>,
     o: Vector2<f32>,
     b: Vector2<f32>,
-    mut radius: f32,
+    corner_style: CornerStyle,
     tag: ColliderTag,
     colliders: &mut Vec<Collider>,
 ) -> (Vector2<f32>, Vector2<f32>) {
@@ -738,77 +1047,388 @@ fn rounded_line_collider(
     let x_hat3 = Vector3::new(x_hat.x, x_hat.y, 0.);
     let y_hat3 = Vector3::new(y_hat.x, y_hat.y, 0.);
     let cross_product = x_hat3.cross(&y_hat3).z;
+    let is_concave = cross_product >= 0.;
 
-    if cross_product >= 0. {
-        radius *= 5.;
-    }
+    let radius = if is_concave {
+        corner_style.concave_radius
+    } else {
+        corner_style.convex_radius
+    };
 
     let c = (x_hat + y_hat).normalize() * radius / ((1. - x_hat.dot(&y_hat)) / 2.).sqrt();
+    // don't let a chamfer cut off more than half of either side
+    let chamfer_distance = radius.min(x.magnitude() / 2.).min(y.magnitude() / 2.);
+
+    // Leave corners which barely turn sharp. Concave corners are fine as long as the rounding
+    // wouldn't move the surface by much. Lines only push out along their normal, so a convex
+    // corner also needs to turn little enough that pushing the player out of the end of the
+    // line is close to pushing them out of the corner.
+    let is_tiny_corner = if is_concave {
+        let surface_moved_by = match corner_style.kind {
+            CornerKind::Round => c.magnitude() - radius,
+            CornerKind::Sharp => 0.,
+            // how far the chamfer line is from the corner
+            CornerKind::Chamfer => chamfer_distance * ((1. + x_hat.dot(&y_hat)) / 2.).sqrt(),
+        };
+
+        surface_moved_by < simplify::TOLERANCE
+    } else {
+        let cos_turn = -x_hat.dot(&y_hat);
+        PLAYER_CIRCLE_APPROX_RADIUS as f32 * (1. - cos_turn) < simplify::TOLERANCE
+    };
 
-    let p1 = x_hat.dot(&c) * x_hat;
-    let p2 = y_hat.dot(&c) * y_hat;
-
-    let circle_center = o + c;
+    if is_tiny_corner || radius <= 0. {
+        if !is_concave && !is_tiny_corner {
+            colliders.push(point_collider(o, tag));
+        }
 
-    if cross_product <= 0. {
-        colliders.push(Collider {
-            kind: ColliderKind::Circle(Circle {
-                position: to_vec(circle_center),
-                radius: Number::from_f32(radius),
-            }),
-            velocity: Vector2D::new(0.into(), 0.into()),
-            tag,
-        });
-    } else {
-        colliders.push(Collider {
-            kind: ColliderKind::Arc(Arc {
-                circle: Circle {
-                    position: to_vec(circle_center),
-                    radius: Number::from_f32(radius),
-                },
-                start_pos: to_vec((p1 - c).normalize()),
-                end_pos: to_vec((p2 - c).normalize()),
-            }),
-            tag,
-            velocity: Vector2D::new(0.into(), 0.into()),
-        })
+        return (o, o);
     }
 
-    (o + p1, o + p2)
+    match corner_style.kind {
+        CornerKind::Round => {
+            let p1 = x_hat.dot(&c) * x_hat;
+            let p2 = y_hat.dot(&c) * y_hat;
+
+            let circle_center = o + c;
+
+            if !is_concave {
+                colliders.push(Collider {
+                    kind: ColliderKind::Circle(Circle {
+                        position: to_vec(circle_center),
+                        radius: Number::from_f32(radius),
+                    }),
+                    velocity: Vector2D::new(0.into(), 0.into()),
+                    tag,
+                });
+            } else {
+                colliders.push(Collider {
+                    kind: ColliderKind::Arc(Arc {
+                        circle: Circle {
+                            position: to_vec(circle_center),
+                            radius: Number::from_f32(radius),
+                        },
+                        start_pos: to_vec((p1 - c).normalize()),
+                        end_pos: to_vec((p2 - c).normalize()),
+                    }),
+                    tag,
+                    velocity: Vector2D::new(0.into(), 0.into()),
+                })
+            }
+
+            (o + p1, o + p2)
+        }
+        CornerKind::Sharp => {
+            // concave corners are handled fine by the two lines meeting
+            if !is_concave {
+                colliders.push(point_collider(o, tag));
+            }
+
+            (o, o)
+        }
+        CornerKind::Chamfer => {
+            let p1 = o + x_hat * chamfer_distance;
+            let p2 = o + y_hat * chamfer_distance;
+
+            colliders.extend(get_line_colliders(p1, p2, tag));
+
+            // the chamfer turns the corner into two smaller convex corners
+            if !is_concave {
+                colliders.push(point_collider(p1, tag));
+                colliders.push(point_collider(p2, tag));
+            }
+
+            (p1, p2)
+        }
+    }
 }
 
 fn to_vec(a: Vector2<f32>) -> Vector2D<Number> {
     (Number::from_f32(a.x), Number::from_f32(a.y)).into()
 }
 
+/// Lines longer than this can overflow the fixed point maths when colliding with them
+const MAX_LINE_LENGTH: f32 = 100.;
+
 fn get_line_colliders(start: Vector2<f32>, end: Vector2<f32>, tag: ColliderTag) -> Vec<Collider> {
     let normalized = (end - start).normalize();
     let normal = Vector2::new(normalized.y, -normalized.x);
     let length = (start - end).magnitude();
 
-    let mut start = start;
-    let mut remaining_length = length;
+    // split into equal parts rather than leaving a tiny line at the end
+    let segment_count = (length / MAX_LINE_LENGTH).ceil().max(1.);
+    let segment_length = length / segment_count;
 
-    let mut ret = vec![];
+    (0..segment_count as usize)
+        .map(|idx| {
+            let segment_start = start + normalized * segment_length * idx as f32;
+            let segment_end = if idx + 1 == segment_count as usize {
+                end
+            } else {
+                segment_start + normalized * segment_length
+            };
 
-    while remaining_length > 0. {
-        let segment_length = remaining_length.min(100.);
-        let end = start + normalized * segment_length;
+            Collider {
+                kind: ColliderKind::Line(Line {
+                    start: to_vec(segment_start),
+                    end: to_vec(segment_end),
+                    normal: to_vec(normal),
+                    length: Number::from_f32(segment_length),
+                }),
+                velocity: Vector2D::new(0.into(), 0.into()),
+                tag,
+            }
+        })
+        .collect()
+}
 
-        ret.push(Collider {
-            kind: ColliderKind::Line(Line {
-                start: to_vec(start),
-                end: to_vec(end),
-                normal: to_vec(normal),
-                length: Number::from_f32(segment_length),
-            }),
-            velocity: Vector2D::new(0.into(), 0.into()),
-            tag,
-        });
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn path(points: &[(i32, i32)], complete: bool, phase: f64) -> Path {
+        Path {
+            name: "test".to_string(),
+            points: points.iter().map(|&(x, y)| (x, y).into()).collect(),
+            complete,
+            one_shot: false,
+            easing: Easing::Linear,
+            phase,
+            speeds: vec![1.; points.len()],
+            pauses: vec![0; points.len()],
+        }
+    }
+
+    #[test]
+    fn path_with_no_phase_starts_at_the_beginning() {
+        let path = path(&[(0, 0), (100, 0)], false, 0.);
+
+        assert_eq!(
+            path.start(0.),
+            PathStart {
+                index: 0,
+                forwards: true,
+                timer: 0.,
+                pause: 0,
+                finished: false,
+            }
+        );
+    }
+
+    #[test]
+    fn ping_pong_phase_goes_backwards_in_second_half() {
+        let path = path(&[(0, 0), (100, 0)], false, 0.75);
+
+        let start = path.start(0.);
+        assert_eq!(start.index, 1);
+        assert!(!start.forwards);
+        assert!((start.timer - 0.5).abs() < 0.001);
+    }
+
+    #[test]
+    fn polygon_phase_wraps_around() {
+        let path = path(&[(0, 0), (100, 0), (100, 100), (0, 100)], true, 1.625);
+
+        let start = path.start(0.);
+        assert_eq!(start.index, 2);
+        assert!(start.forwards);
+        assert!((start.timer - 0.5).abs() < 0.001);
+    }
+
+    #[test]
+    fn phase_can_start_during_a_pause() {
+        let mut path = path(&[(0, 0), (100, 0)], false, 0.6);
+        path.pauses = vec![0, 100];
+
+        let start = path.start(0.);
+        assert_eq!(start.index, 1);
+        assert!(!start.forwards);
+        assert_eq!(start.pause, 20);
+    }
+
+    #[test]
+    fn one_shot_phase_in_the_last_pause_stays_finished() {
+        let points = [(0, 0), (100, 0), (100, 100)];
+
+        let mut open = path(&points, false, 0.9);
+        open.one_shot = true;
+        open.pauses = vec![0, 0, 100];
+
+        let start = open.start(0.);
+        assert_eq!(start.index, 2);
+        assert_eq!(start.pause, 30);
+        assert!(start.finished);
+
+        let mut complete = path(&points, true, 0.95);
+        complete.one_shot = true;
+        complete.pauses = vec![100, 0, 0];
+
+        let start = complete.start(0.);
+        assert_eq!(start.index, 0);
+        assert!(start.finished);
+    }
+
+    fn corner_colliders(points: &[(f32, f32)]) -> Vec<Collider> {
+        styled_corner_colliders(points, CornerStyle::default())
+    }
+
+    fn styled_corner_colliders(points: &[(f32, f32)], corner_style: CornerStyle) -> Vec<Collider> {
+        let mut colliders = Vec::new();
+        handle_points_for_collider(
+            Vector2::zeros(),
+            corner_style,
+            points,
+            &mut colliders,
+            ColliderTag::CollisionOnly,
+            false,
+        );
+
+        colliders
+    }
+
+    #[test]
+    fn slight_bend_has_no_rounded_corner() {
+        let colliders = corner_colliders(&[(0., 0.), (50., 0.), (100., 5.)]);
+
+        assert!(colliders
+            .iter()
+            .all(|x| matches!(x.kind, ColliderKind::Line(_))));
+    }
+
+    #[test]
+    fn right_angle_has_rounded_corner() {
+        let colliders = corner_colliders(&[(0., 0.), (50., 0.), (50., 50.)]);
+
+        assert_eq!(
+            colliders
+                .iter()
+                .filter(|x| matches!(x.kind, ColliderKind::Circle(_)))
+                .count(),
+            1
+        );
+    }
+
+    #[test]
+    fn long_lines_are_split_evenly() {
+        let colliders = get_line_colliders(
+            Vector2::new(0., 0.),
+            Vector2::new(150., 0.),
+            ColliderTag::CollisionOnly,
+        );
+
+        assert_eq!(colliders.len(), 2);
+        assert!(colliders.iter().all(|x| match &x.kind {
+            ColliderKind::Line(line) => line.length == 75.into(),
+            _ => false,
+        }));
+    }
+
+    fn style(kind: CornerKind) -> CornerStyle {
+        CornerStyle {
+            kind,
+            ..CornerStyle::default()
+        }
+    }
 
-        start = end;
-        remaining_length -= segment_length;
+    fn lines(colliders: &[Collider]) -> Vec<&Line> {
+        colliders
+            .iter()
+            .filter_map(|x| match &x.kind {
+                ColliderKind::Line(line) => Some(line),
+                _ => None,
+            })
+            .collect()
     }
 
-    ret
+    #[test]
+    fn sharp_convex_corner_pushes_away_from_the_corner() {
+        // a ledge, walking right along the top and then down the right hand side
+        let colliders =
+            styled_corner_colliders(&[(0., 0.), (50., 0.), (50., 50.)], style(CornerKind::Sharp));
+
+        let corner = colliders
+            .iter()
+            .find_map(|x| match &x.kind {
+                ColliderKind::Circle(circle) => Some(circle),
+                _ => None,
+            })
+            .expect("Sharp convex corner should have a point collider");
+
+        assert_eq!(corner.position, (50, 0).into());
+        assert_eq!(corner.radius, 0.into());
+
+        let lines = lines(&colliders);
+        assert_eq!(lines[0].end, (50, 0).into());
+        assert_eq!(lines[1].start, (50, 0).into());
+    }
+
+    #[test]
+    fn sharp_concave_corner_is_just_the_lines() {
+        let colliders = styled_corner_colliders(
+            &[(0., 0.), (50., 0.), (50., -50.)],
+            style(CornerKind::Sharp),
+        );
+
+        assert!(colliders
+            .iter()
+            .all(|x| matches!(x.kind, ColliderKind::Line(_))));
+    }
+
+    #[test]
+    fn chamfer_normal_faces_between_the_sides() {
+        let colliders = styled_corner_colliders(
+            &[(0., 0.), (50., 0.), (50., 50.)],
+            style(CornerKind::Chamfer),
+        );
+
+        let lines = lines(&colliders);
+        assert_eq!(lines.len(), 3);
+
+        let top = lines[1].normal;
+        let side = lines[2].normal;
+        let chamfer = lines[0].normal;
+
+        assert!(chamfer.dot(top) > 0.into());
+        assert!(chamfer.dot(side) > 0.into());
+        assert_eq!(
+            colliders
+                .iter()
+                .filter(|x| matches!(x.kind, ColliderKind::Circle(_)))
+                .count(),
+            2
+        );
+    }
+
+    #[test]
+    fn concave_chamfer_normal_faces_between_the_sides() {
+        // a floor running into a wall which goes up from it
+        let colliders = styled_corner_colliders(
+            &[(0., 0.), (50., 0.), (50., -50.)],
+            style(CornerKind::Chamfer),
+        );
+
+        let lines = lines(&colliders);
+        assert_eq!(lines.len(), 3);
+
+        let floor = lines[1].normal;
+        let wall = lines[2].normal;
+        let chamfer = lines[0].normal;
+
+        assert!(chamfer.dot(floor) > 0.into());
+        assert!(chamfer.dot(wall) > 0.into());
+        assert!(colliders
+            .iter()
+            .all(|x| matches!(x.kind, ColliderKind::Line(_))));
+    }
+
+    #[test]
+    fn concave_chamfer_on_short_sides_is_left_sharp() {
+        // the chamfer can only cut 0.6 pixels along each side, which barely moves the surface
+        let colliders = styled_corner_colliders(
+            &[(0., 0.), (1.2, 0.), (1.2, -1.2)],
+            style(CornerKind::Chamfer),
+        );
+
+        assert_eq!(lines(&colliders).len(), 2);
+    }
 }
@@ -1,59 +1,296 @@
 #![feature(int_roundings)]
 
-use std::{error::Error, path::Path};
+use std::{
+    collections::{BTreeSet, HashMap},
+    error::Error,
+    path::Path,
+};
 
-use collider_extract::assemble_colliders;
+use collider_extract::{assemble_colliders, quote_vec};
+use itertools::Itertools;
+use maptile_extract::TileSetting;
 use proc_macro2::TokenStream;
-use quote::quote;
+use quote::{format_ident, quote};
 use scroll_stop::get_scroll_stops;
-use tiled::{Loader, Map, TileLayer};
+use tiled::{InfiniteTileLayer, Layer, Loader, Map, PropertyValue, TileLayer};
+use tileset_registry::TilesetRegistry;
 use util::Number;
+use world::{level_objects, MapPart};
 
+mod camera_zone;
 mod collider_extract;
 mod maptile_extract;
+mod properties;
 
 mod scroll_stop;
+mod simplify;
 mod spiral;
+mod tile_collider_extract;
+mod tileset_registry;
+mod world;
 
-pub fn compile_map(path: impl AsRef<Path>) -> Result<String, Box<dyn Error>> {
+/// The GBA has 4 backgrounds, and one of them is used for the stars
+const MAX_TILE_LAYERS: usize = 3;
+
+/// Settings for how the levels are compiled
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CompileOptions {
+    /// Compile layers and objects which are hidden in Tiled as if they were visible, so that work
+    /// in progress can be tried out in the game
+    pub include_hidden: bool,
+}
+
+/// Compiles each `(name, path)` map into its own level module, along with the tilesets and
+/// images shared between all the levels, and a `LEVELS` list of them.
+///
+/// The path can also be a Tiled `.world` file, in which case its maps are placed where they are in
+/// the world and stitched together into one level.
+pub fn compile_levels<P: AsRef<Path>>(
+    levels: &[(&str, P)],
+    options: CompileOptions,
+) -> Result<String, Box<dyn Error>> {
     let mut loader = Loader::new();
-    let map = loader.load_tmx_map(path)?;
+    let mut maps = Vec::new();
+    let mut level_offsets = Vec::new();
 
-    let planet_maptile_phf = tiles_for_layer(&map, "Planets");
-    let platform_maptile_phf = tiles_for_layer(&map, "Platforms");
+    for (_, path) in levels {
+        let mut offsets = Vec::new();
+        for (map_path, offset) in world::level_maps(path.as_ref())? {
+            offsets.push((maps.len(), offset));
+            maps.push(loader.load_tmx_map(map_path)?);
+        }
+        level_offsets.push(offsets);
+    }
 
-    let planet_maptile_phf_code = planet_maptile_phf.build();
-    let platform_maptile_phf_code = platform_maptile_phf.build();
-    Ok(format!(
-        "{}\n\n{}{planet_maptile_phf_code};\n\n{}{platform_maptile_phf_code};\n\n{}\n\n{};\n{}",
-        assemble_colliders(&map),
-        quote! {
-            pub static PLANET_MAP_TILES: phf::Map<[i32; 2], &'static [super::MapTileSetting]> =
-        },
+    let level_parts: Vec<Vec<_>> = level_offsets
+        .into_iter()
+        .map(|offsets| {
+            offsets
+                .into_iter()
+                .map(|(index, offset)| MapPart {
+                    map: &maps[index],
+                    index,
+                    offset,
+                    include_hidden: options.include_hidden,
+                })
+                .collect()
+        })
+        .collect();
+
+    let used_tilesets: Vec<_> = maps
+        .iter()
+        .map(|map| (map, used_tilesets(map, options.include_hidden)))
+        .collect();
+    let registry = TilesetRegistry::new(&used_tilesets);
+
+    let level_names: Vec<_> = levels.iter().map(|(name, _)| *name).collect();
+    for name in &level_names {
+        assert!(
+            name.chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_'),
+            "Level name {name} should be snake_case"
+        );
+    }
+    let modules: Vec<_> = level_names
+        .iter()
+        .map(|name| format_ident!("level_{}", name))
+        .collect();
+
+    let mut output = format!(
+        "{}\n\n{}\n\n{}\n\n{}\n\n{}\n\n",
+        registry.quote(),
+        quote_dynamic_collider_images(&level_parts),
+        collider_extract::quote_box_sizes(),
+        scroll_stop::quote_box_size(),
+        camera_zone::quote_box_size(),
+    );
+
+    for ((name, parts), module) in level_names.iter().zip(&level_parts).zip(&modules) {
+        output.push_str(&format!(
+            "pub mod {module} {{\nuse super::*;\n\n{}\n}}\n\n",
+            compile_level(name, parts, &registry, &level_names)
+        ));
+    }
+
+    output.push_str(
+        &quote! {
+            pub static LEVELS: &[&super::Level] = &[#(&#modules::LEVEL),*];
+        }
+        .to_string(),
+    );
+
+    Ok(output)
+}
+
+fn compile_level(
+    name: &str,
+    parts: &[MapPart],
+    registry: &TilesetRegistry,
+    level_names: &[&str],
+) -> String {
+    format!(
+        "{}\n\n{}\n\n{}\n\n{};\n{}\n\n{}\n\n{}\n\n{}\n\n{}\n\n{}",
+        assemble_colliders(parts),
+        get_tile_layers(parts, registry),
+        get_start_point(parts),
+        get_scroll_stops(parts),
+        camera_zone::get_camera_zones(parts),
+        get_powerups(name, parts),
+        get_mission_logs(name, parts),
+        get_exits(parts, level_names),
+        get_finish(parts),
         quote! {
-            pub static PLATFORM_MAP_TILES: phf::Map<[i32; 2], &'static [super::MapTileSetting]> =
-        },
-        get_start_point(&map),
-        get_scroll_stops(&map),
-        get_powerups(&map),
-    ))
-}
-
-fn tiles_for_layer(map: &Map, name: &str) -> phf_codegen::Map<[i32; 2]> {
-    let infinite_map = map
-        .layers()
-        .find_map(|layer| {
-            if let Some(TileLayer::Infinite(infinite_layer)) = layer.as_tile_layer() {
-                if layer.name == name {
-                    return Some(infinite_layer);
+            pub static LEVEL: super::Level = super::Level {
+                name: #name,
+                start_point: START_POINT,
+                camera_start: CAMERA_START,
+                tile_layers: TILE_LAYERS,
+                power_ups: POWER_UPS,
+                mission_logs: MISSION_LOGS,
+                exits: EXITS,
+                finish: FINISH,
+                recovery_points: RECOVERY_POINTS,
+                nearby_colliders: &NEARBY_COLLIDERS,
+                path_lookup: &PATH_LOOKUP,
+                scroll_stops: &SCROLL_STOPS,
+                camera_zones: &CAMERA_ZONES,
+            };
+        }
+    )
+}
+
+/// The images of moving objects are shared between levels, so they can all be given a sprite
+fn quote_dynamic_collider_images(level_parts: &[Vec<MapPart>]) -> TokenStream {
+    let images = level_parts
+        .iter()
+        .flat_map(|parts| collider_extract::dynamic_collider_images(parts))
+        .unique()
+        .map(|x| format_ident!("{}", x));
+
+    quote! {
+        #[derive(Clone, Copy)]
+        pub enum DynamicColliderImage {
+            #(#images),*
+        }
+    }
+}
+
+fn used_tilesets(map: &Map, include_hidden: bool) -> BTreeSet<usize> {
+    game_tile_layers(map, include_hidden)
+        .flat_map(|(_, layer)| {
+            maptile_extract::extract_tiles([(layer, 0, (0, 0))])
+                .into_values()
+                .flatten()
+        })
+        .filter(|tile_setting| tile_setting.tile_id != u16::MAX)
+        .map(|tile_setting| tile_setting.tileset)
+        .collect()
+}
+
+/// The tile layers which end up in the game
+fn game_tile_layers(
+    map: &Map,
+    include_hidden: bool,
+) -> impl Iterator<Item = (Layer<'_>, InfiniteTileLayer<'_>)> {
+    map.layers()
+        .filter(|layer| !properties::get_bool(&layer.properties, "editor_only").unwrap_or(false))
+        .filter(move |layer| layer.visible || include_hidden)
+        .filter_map(|layer| match layer.as_tile_layer() {
+            Some(TileLayer::Infinite(infinite_layer)) => Some((layer, infinite_layer)),
+            Some(TileLayer::Finite(_)) => panic!("Tile layer '{}' should be infinite", layer.name),
+            None => None,
+        })
+}
+
+fn get_tile_layers(parts: &[MapPart], registry: &TilesetRegistry) -> String {
+    // layers with the same name in different maps of a world are joined into one layer
+    let mut layers: Vec<(Layer, Vec<_>)> = Vec::new();
+    for part in parts {
+        for (layer, infinite_layer) in part.tile_layers() {
+            let tiles = (infinite_layer, part.index, part.tile_offset(&layer));
+
+            match layers.iter_mut().find(|(x, _)| x.name == layer.name) {
+                Some((first, layer_parts)) => {
+                    assert!(
+                        first.parallax_x == layer.parallax_x
+                            && first.parallax_y == layer.parallax_y,
+                        "Layer '{}' should have the same parallax in every map",
+                        layer.name
+                    );
+                    layer_parts.push(tiles);
                 }
+                None => layers.push((layer, vec![tiles])),
             }
-            None
-        })
-        .expect("Could not find '{name}' map");
+        }
+    }
+
+    assert!(
+        layers.len() <= MAX_TILE_LAYERS,
+        "Can only have {MAX_TILE_LAYERS} tile layers, but found {}",
+        layers.len()
+    );
+
+    let mut output = String::new();
+    let mut tile_layers = Vec::new();
+    let layer_count = layers.len();
+
+    for (idx, (layer, layer_parts)) in layers.into_iter().enumerate() {
+        let tiles_ident = format_ident!("TILE_LAYER_{}", idx);
+        let tiles = maptile_extract::extract_tiles(layer_parts);
+
+        output.push_str(&format!(
+            "{}{};\n\n",
+            quote! {
+                static #tiles_ident: phf::Map<[i32; 2], &'static [super::MapTileSetting]> =
+            },
+            tiles_for_layer(tiles, registry).build()
+        ));
+
+        let name = &layer.name;
+        let parallax = quote_vec(
+            (
+                Number::from_f32(layer.parallax_x),
+                Number::from_f32(layer.parallax_y),
+            )
+                .into(),
+        );
+        // layers later in the list are drawn on top in Tiled
+        let priority = properties::get_int(&layer.properties, "priority")
+            .map(|priority| {
+                u8::try_from(priority)
+                    .ok()
+                    .filter(|&priority| priority < 4)
+                    .unwrap_or_else(|| panic!("Priority of '{name}' should be between 0 and 3"))
+            })
+            .unwrap_or((layer_count - idx - 1) as u8);
+        // hidden layers are only compiled when debugging them, and then they should be seen
+        let visible = layer.visible || parts.iter().any(|part| part.include_hidden);
 
-    let tiles = maptile_extract::extract_tiles(&infinite_map);
+        tile_layers.push(quote! {
+            super::TileLayer {
+                name: #name,
+                parallax: #parallax,
+                priority: #priority,
+                visible: #visible,
+                tiles: &#tiles_ident,
+            }
+        });
+    }
 
+    output.push_str(
+        &quote! {
+            pub static TILE_LAYERS: &[super::TileLayer] = &[#(#tile_layers),*];
+        }
+        .to_string(),
+    );
+
+    output
+}
+
+fn tiles_for_layer(
+    tiles: HashMap<(i32, i32), Vec<TileSetting>>,
+    registry: &TilesetRegistry,
+) -> phf_codegen::Map<[i32; 2]> {
     let mut maptile_phf = phf_codegen::Map::new();
 
     for (key, tiles) in tiles {
@@ -64,20 +301,13 @@ fn tiles_for_layer(map: &Map, name: &str) -> phf_codegen::Map<[i32; 2]> {
             .iter()
             .map(|tile_setting| {
                 if tile_setting.tile_id == u16::MAX {
-                    quote!(super::BLANK_TILE)
+                    quote!(BLANK_TILE)
                 } else {
                     let tile_id = tile_setting.tile_id;
                     let hflip = tile_setting.hflip;
                     let vflip = tile_setting.vflip;
-                    let map_tile_set = match tile_setting.tileset {
-                        maptile_extract::GameTileSet::Planets => quote!(super::MapTileSet::Planets),
-                        maptile_extract::GameTileSet::Platforms => {
-                            quote!(super::MapTileSet::Platforms)
-                        }
-                        maptile_extract::GameTileSet::Planets2 => {
-                            quote!(super::MapTileSet::Planets2)
-                        }
-                    };
+                    let map_tile_set = registry.variant(tile_setting.map, tile_setting.tileset);
+                    let animated = tile_setting.animated;
 
                     quote!(
                         super::MapTileSetting {
@@ -85,6 +315,7 @@ fn tiles_for_layer(map: &Map, name: &str) -> phf_codegen::Map<[i32; 2]> {
                             hflip: #hflip,
                             vflip: #vflip,
                             map_tile_set: #map_tile_set,
+                            animated: #animated,
                         }
                     )
                 }
@@ -97,20 +328,27 @@ fn tiles_for_layer(map: &Map, name: &str) -> phf_codegen::Map<[i32; 2]> {
     maptile_phf
 }
 
-fn get_start_point(map: &Map) -> TokenStream {
-    let layer = map
-        .layers()
-        .filter(|x| x.name == "Start")
-        .find_map(|x| x.as_object_layer())
-        .unwrap();
-    let start_object = layer.objects().find(|x| x.name == "PLAYER").unwrap();
-    let camera = layer.objects().find(|x| x.name == "CAMERA").unwrap();
+fn get_start_point(parts: &[MapPart]) -> TokenStream {
+    let start_objects = level_objects(parts, "Start");
+    // there should be exactly one of each, even when the level is made of several maps
+    let find = |name: &str| {
+        let mut objects = start_objects.iter().filter(|(x, _)| x.name == name);
+        let (object, offset) = objects
+            .next()
+            .unwrap_or_else(|| panic!("Level should have a {name} on a Start layer"));
+        assert!(
+            objects.next().is_none(),
+            "Level should only have one {name}"
+        );
 
-    let x = Number::from_f32(start_object.x).to_raw();
-    let y = Number::from_f32(start_object.y).to_raw();
+        (
+            Number::from_f32(object.x + offset.x).to_raw(),
+            Number::from_f32(object.y + offset.y).to_raw(),
+        )
+    };
 
-    let cx = Number::from_f32(camera.x).to_raw();
-    let cy = Number::from_f32(camera.y).to_raw();
+    let (x, y) = find("PLAYER");
+    let (cx, cy) = find("CAMERA");
 
     quote! {
         pub const START_POINT: Vector2D<Number> = Vector2D::new(Number::from_raw(#x), Number::from_raw(#y));
@@ -119,33 +357,35 @@ fn get_start_point(map: &Map) -> TokenStream {
     }
 }
 
-fn get_powerups(map: &Map) -> TokenStream {
-    let layer = map
-        .layers()
-        .find_map(|x| {
-            if x.name == "Items" {
-                x.as_object_layer()
-            } else {
-                None
-            }
-        })
-        .unwrap();
+fn get_powerups(level: &str, parts: &[MapPart]) -> TokenStream {
+    let objects = level_objects(parts, "Items");
+    assert_items_fit_in_save(level, "power ups", objects.len());
+
+    let powerups = objects.iter().map(|(obj, offset)| {
+        let name = &obj.name;
+        let x = Number::from_f32(obj.x + offset.x).to_raw();
+        let y = Number::from_f32(obj.y + offset.y).to_raw();
+
+        let sprite = properties::get_string(&obj.properties, "sprite")
+            .unwrap_or_else(|| panic!("Power up {name} should have a sprite"));
 
-    let powerups = layer.objects().map(|obj| {
-        let x = Number::from_f32(obj.x).to_raw();
-        let y = Number::from_f32(obj.y).to_raw();
+        let mut modifier_properties: Vec<_> = obj
+            .properties
+            .iter()
+            .filter(|(property, _)| *property != "sprite")
+            .collect();
+        modifier_properties.sort_by_key(|(property, _)| *property);
 
-        let powerup = match obj.name.as_str() {
-            "Jump Boost" => quote! { PowerUpKind::JumpBoost },
-            "Dash" => quote! { PowerUpKind::Dash },
-            "Double Jump" => quote! { PowerUpKind::DoubleJump },
-            boost_name => todo!("Unknown boost {boost_name}"),
-        };
+        let modifiers = modifier_properties
+            .into_iter()
+            .map(|(property, value)| quote_stat_modifier(name, property, value));
 
         quote! {
             PowerUp {
-                kind: #powerup,
+                name: #name,
                 location: Vector2D::new(Number::from_raw(#x), Number::from_raw(#y)),
+                sprite: #sprite,
+                modifiers: &[#(#modifiers),*],
             }
         }
     });
@@ -154,3 +394,280 @@ fn get_powerups(map: &Map) -> TokenStream {
         pub static POWER_UPS: &[PowerUp] = &[#(#powerups),*];
     }
 }
+
+/// Which power ups have been picked up and which logs have been seen are saved as one bit each,
+/// and there's only room for so many of them
+fn assert_items_fit_in_save(level: &str, items: &str, count: usize) {
+    assert!(
+        count <= util::MAX_SAVED_ITEMS,
+        "Level {level} has {count} {items}, but only {} can be saved",
+        util::MAX_SAVED_ITEMS
+    );
+}
+
+/// How close the player needs to get to a log to play it, unless it has a `radius` property
+const DEFAULT_LOG_RADIUS: i32 = 64;
+
+fn get_mission_logs(level: &str, parts: &[MapPart]) -> TokenStream {
+    let logs = mission_logs(parts);
+    assert_items_fit_in_save(level, "mission logs", logs.len());
+
+    let logs = logs.into_iter().map(|(x, y, radius, text)| {
+        quote! {
+            MissionLog {
+                point: Vector2D::new(#x, #y),
+                radius: #radius,
+                text: #text,
+            }
+        }
+    });
+
+    quote! {
+        pub static MISSION_LOGS: &[MissionLog] = &[#(#logs),*];
+    }
+}
+
+/// The position, radius and text of each log on the "Logs" layer, in the order they play in
+fn mission_logs(parts: &[MapPart]) -> Vec<(i32, i32, i32, String)> {
+    let mut logs: Vec<_> = level_objects(parts, "Logs")
+        .into_iter()
+        .map(|(obj, offset)| {
+            let text = properties::get_string(&obj.properties, "text")
+                .unwrap_or_else(|| panic!("Log {} should have some text", obj.id()));
+            let radius = properties::get_float(&obj.properties, "radius")
+                .map_or(DEFAULT_LOG_RADIUS, |radius| radius as i32);
+            // logs which can play in the same place play in this order
+            let order = properties::get_int(&obj.properties, "order").unwrap_or(0);
+
+            let x = (obj.x + offset.x) as i32;
+            let y = (obj.y + offset.y) as i32;
+
+            (order, (x, y, radius, text.to_string()))
+        })
+        .collect();
+    // stable, so logs with the same order stay in the order they are in Tiled
+    logs.sort_by_key(|(order, _)| *order);
+
+    logs.into_iter().map(|(_, log)| log).collect()
+}
+
+/// How close the player needs to get to an exit to use it, unless it has a `radius` property
+const DEFAULT_EXIT_RADIUS: i32 = 16;
+
+/// Places where the player leaves for another level, on an optional "Exits" layer
+fn get_exits(parts: &[MapPart], level_names: &[&str]) -> TokenStream {
+    let exits = exits(parts, level_names)
+        .into_iter()
+        .map(|(x, y, radius, level)| {
+            quote! {
+                LevelExit {
+                    point: Vector2D::new(#x, #y),
+                    radius: #radius,
+                    level: #level,
+                }
+            }
+        });
+
+    quote! {
+        pub static EXITS: &[LevelExit] = &[#(#exits),*];
+    }
+}
+
+/// The position and radius of each exit, and the name of the level it goes to
+fn exits(parts: &[MapPart], level_names: &[&str]) -> Vec<(i32, i32, i32, String)> {
+    level_objects(parts, "Exits")
+        .into_iter()
+        .map(|(obj, offset)| {
+            let level = properties::get_string(&obj.properties, "level")
+                .unwrap_or_else(|| panic!("Exit {} should say which level it goes to", obj.id()));
+            assert!(
+                level_names.contains(&level),
+                "Exit {} goes to unknown level {level}",
+                obj.id()
+            );
+
+            let x = (obj.x + offset.x) as i32;
+            let y = (obj.y + offset.y) as i32;
+            let radius = properties::get_float(&obj.properties, "radius")
+                .map_or(DEFAULT_EXIT_RADIUS, |radius| radius as i32);
+
+            (x, y, radius, level.to_string())
+        })
+        .collect()
+}
+
+/// How close the player needs to get to the finish, unless it has a `radius` property
+const DEFAULT_FINISH_RADIUS: i32 = 24;
+
+/// Where time attack runs end, on an optional "Finish" layer
+fn get_finish(parts: &[MapPart]) -> TokenStream {
+    let finish = match finish(parts) {
+        Some((x, y, radius)) => quote! {
+            Some(Finish {
+                point: Vector2D::new(#x, #y),
+                radius: #radius,
+            })
+        },
+        None => quote! { None },
+    };
+
+    quote! {
+        pub const FINISH: Option<Finish> = #finish;
+    }
+}
+
+/// The position and radius of the finish, if the level has one
+fn finish(parts: &[MapPart]) -> Option<(i32, i32, i32)> {
+    let finishes = level_objects(parts, "Finish");
+    assert!(finishes.len() <= 1, "Level should only have one finish");
+
+    finishes.first().map(|(obj, offset)| {
+        let x = (obj.x + offset.x) as i32;
+        let y = (obj.y + offset.y) as i32;
+        let radius = properties::get_float(&obj.properties, "radius")
+            .map_or(DEFAULT_FINISH_RADIUS, |radius| radius as i32);
+
+        (x, y, radius)
+    })
+}
+
+/// Power up properties are named `<operation>_<stat>`, like `add_max_jumps` or `set_can_dash`
+fn quote_stat_modifier(power_up: &str, property: &str, value: &PropertyValue) -> TokenStream {
+    let (operation, stat) = property
+        .split_once('_')
+        .unwrap_or_else(|| panic!("Power up {power_up} has unknown property {property}"));
+
+    let operation = match operation {
+        "set" => quote!(StatOperation::Set),
+        "add" => quote!(StatOperation::Add),
+        "multiply" => quote!(StatOperation::Multiply),
+        _ => panic!(
+            "Power up {power_up} has unknown operation {operation}, should be set, add or multiply"
+        ),
+    };
+
+    let stat = match stat {
+        "ground_speed" => quote!(PlayerStat::GroundSpeed),
+        "air_speed" => quote!(PlayerStat::AirSpeed),
+        "jump_speed" => quote!(PlayerStat::JumpSpeed),
+        "max_jumps" => quote!(PlayerStat::MaxJumps),
+        "can_dash" => quote!(PlayerStat::CanDash),
+        _ => panic!("Power up {power_up} modifies unknown stat {stat}"),
+    };
+
+    let amount = match value {
+        PropertyValue::FloatValue(value) => Number::from_f32(*value),
+        PropertyValue::IntValue(value) => Number::new(*value),
+        PropertyValue::BoolValue(value) => Number::new(*value as i32),
+        _ => panic!("Property {property} of power up {power_up} should be a number or bool"),
+    }
+    .to_raw();
+
+    quote! {
+        StatModifier {
+            stat: #stat,
+            operation: #operation,
+            amount: Number::from_raw(#amount),
+        }
+    }
+}
+
+/// Loads one of the small maps kept for testing the compiler
+#[cfg(test)]
+fn load_fixture(name: &str) -> Map {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("fixtures")
+        .join(name);
+
+    Loader::new()
+        .load_tmx_map(&path)
+        .unwrap_or_else(|err| panic!("Could not load fixture {name}, {err}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::Vector2;
+
+    use super::*;
+
+    fn part(map: &Map) -> MapPart<'_> {
+        MapPart {
+            map,
+            index: 0,
+            offset: Vector2::zeros(),
+            include_hidden: false,
+        }
+    }
+
+    #[test]
+    fn mission_logs_play_in_order_then_in_the_order_they_are_in_tiled() {
+        let map = load_fixture("logs.tmx");
+
+        let texts: Vec<_> = mission_logs(&[part(&map)])
+            .into_iter()
+            .map(|(_, _, _, text)| text)
+            .collect();
+
+        assert_eq!(texts, ["unordered", "tied first", "tied second", "last"]);
+    }
+
+    #[test]
+    fn mission_logs_default_their_radius() {
+        let map = load_fixture("logs.tmx");
+
+        let logs = mission_logs(&[part(&map)]);
+
+        assert_eq!(
+            logs[0],
+            (40, 16, DEFAULT_LOG_RADIUS, "unordered".to_string())
+        );
+        assert_eq!(logs[1].2, 32);
+    }
+
+    #[test]
+    fn levels_exit_to_each_other_and_only_the_last_has_a_finish() {
+        let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures");
+        let levels = [
+            ("first", fixtures.join("first.tmx")),
+            ("second", fixtures.join("second.tmx")),
+        ];
+
+        let output = compile_levels(&levels, CompileOptions::default()).unwrap();
+        assert!(output.contains("pub mod level_first"));
+        assert!(output.contains("pub mod level_second"));
+
+        let level_names = ["first", "second"];
+        let first = load_fixture("first.tmx");
+        let second = load_fixture("second.tmx");
+
+        assert_eq!(
+            exits(&[part(&first)], &level_names),
+            [(120, 40, DEFAULT_EXIT_RADIUS, "second".to_string())]
+        );
+        assert_eq!(
+            exits(&[part(&second)], &level_names),
+            [(16, 40, 8, "first".to_string())]
+        );
+
+        assert_eq!(finish(&[part(&first)]), None);
+        assert_eq!(
+            finish(&[part(&second)]),
+            Some((120, 40, DEFAULT_FINISH_RADIUS))
+        );
+    }
+
+    #[test]
+    #[should_panic = "Exit 4 goes to unknown level second"]
+    fn exits_have_to_go_to_a_level_being_compiled() {
+        let first = load_fixture("first.tmx");
+
+        exits(&[part(&first)], &["first"]);
+    }
+
+    #[test]
+    #[should_panic = "Level main has 129 power ups, but only 128 can be saved"]
+    fn levels_cannot_have_more_items_than_can_be_saved() {
+        assert_items_fit_in_save("main", "power ups", util::MAX_SAVED_ITEMS);
+        assert_items_fit_in_save("main", "power ups", util::MAX_SAVED_ITEMS + 1);
+    }
+}
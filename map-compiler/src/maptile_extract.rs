@@ -2,62 +2,66 @@ use std::collections::HashMap;
 
 use tiled::{ChunkData, InfiniteTileLayer};
 
-pub enum GameTileSet {
-    Planets,
-    Platforms,
-    Planets2,
-}
-
+#[derive(Clone)]
 pub struct TileSetting {
-    pub tileset: GameTileSet,
+    /// Index of the map in the list of every map being compiled
+    pub map: usize,
+    /// Index of the tileset in the map's list of tilesets
+    pub tileset: usize,
     pub hflip: bool,
     pub vflip: bool,
     pub tile_id: u16,
+    /// Whether this tile has an animation in Tiled
+    pub animated: bool,
 }
 
-pub fn extract_tiles(map_tiles: &InfiniteTileLayer) -> HashMap<(i32, i32), Vec<TileSetting>> {
+const BLANK: TileSetting = TileSetting {
+    map: 0,
+    tileset: 0,
+    tile_id: u16::MAX,
+    hflip: false,
+    vflip: false,
+    animated: false,
+};
+
+/// Splits the tiles of the layers into 8x8 chunks. Each layer comes with the index of its map and
+/// the offset in tiles of that map, so that layers from several maps can make up one layer.
+pub fn extract_tiles<'a>(
+    layers: impl IntoIterator<Item = (InfiniteTileLayer<'a>, usize, (i32, i32))>,
+) -> HashMap<(i32, i32), Vec<TileSetting>> {
     // if this changes, then 😭
     assert_eq!(ChunkData::HEIGHT, 16);
     assert_eq!(ChunkData::WIDTH, 16);
     assert_eq!(ChunkData::TILE_COUNT, 256);
 
-    let mut tiles = HashMap::new();
+    let mut tiles: HashMap<(i32, i32), Vec<TileSetting>> = HashMap::new();
 
-    for ((super_chunk_x, super_chunk_y), chunk) in map_tiles.chunks() {
-        // internally split these into 8x8 chunks
-        for chunk_y in 0..2 {
-            for chunk_x in 0..2 {
-                let mut chunk_data = vec![];
+    for (layer, map, (offset_x, offset_y)) in layers {
+        for ((super_chunk_x, super_chunk_y), chunk) in layer.chunks() {
+            for y in 0..ChunkData::HEIGHT as i32 {
+                for x in 0..ChunkData::WIDTH as i32 {
+                    let tile_x = super_chunk_x * ChunkData::WIDTH as i32 + x + offset_x;
+                    let tile_y = super_chunk_y * ChunkData::HEIGHT as i32 + y + offset_y;
 
-                for y in chunk_y * 8..(chunk_y + 1) * 8 {
-                    for x in chunk_x * 8..(chunk_x + 1) * 8 {
-                        if let Some(tile) = chunk.get_tile(x, y) {
-                            chunk_data.push(TileSetting {
-                                tileset: match tile.get_tileset().name.as_str() {
-                                    "planets" => GameTileSet::Planets,
-                                    "planets2" => GameTileSet::Planets2,
-                                    "platforms" => GameTileSet::Platforms,
-                                    name => panic!("Unknown tile set {name}"),
-                                },
+                    // internally split these into 8x8 chunks
+                    let chunk_data = tiles
+                        .entry((tile_x.div_floor(8), tile_y.div_floor(8)))
+                        .or_insert_with(|| vec![BLANK; 64]);
+
+                    if let Some(tile) = chunk.get_tile(x, y) {
+                        chunk_data[(tile_y.rem_euclid(8) * 8 + tile_x.rem_euclid(8)) as usize] =
+                            TileSetting {
+                                map,
+                                tileset: tile.tileset_index(),
                                 tile_id: tile.id() as u16,
                                 hflip: tile.flip_h,
                                 vflip: tile.flip_v,
-                            });
-                        } else {
-                            chunk_data.push(TileSetting {
-                                tileset: GameTileSet::Planets,
-                                tile_id: u16::MAX,
-                                hflip: false,
-                                vflip: false,
-                            });
-                        }
+                                animated: tile
+                                    .get_tile()
+                                    .is_some_and(|tile| tile.animation.is_some()),
+                            };
                     }
                 }
-
-                tiles.insert(
-                    (super_chunk_x * 2 + chunk_x, super_chunk_y * 2 + chunk_y),
-                    chunk_data,
-                );
             }
         }
     }
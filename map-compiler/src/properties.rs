@@ -0,0 +1,30 @@
+use tiled::{Properties, PropertyValue};
+
+pub fn get_float(properties: &Properties, name: &str) -> Option<f32> {
+    properties.get(name).map(|value| match value {
+        PropertyValue::FloatValue(value) => *value,
+        PropertyValue::IntValue(value) => *value as f32,
+        _ => panic!("Property {name} should be a float, got {value:?}"),
+    })
+}
+
+pub fn get_int(properties: &Properties, name: &str) -> Option<i32> {
+    properties.get(name).map(|value| match value {
+        PropertyValue::IntValue(value) => *value,
+        _ => panic!("Property {name} should be an int, got {value:?}"),
+    })
+}
+
+pub fn get_bool(properties: &Properties, name: &str) -> Option<bool> {
+    properties.get(name).map(|value| match value {
+        PropertyValue::BoolValue(value) => *value,
+        _ => panic!("Property {name} should be a bool, got {value:?}"),
+    })
+}
+
+pub fn get_string<'a>(properties: &'a Properties, name: &str) -> Option<&'a str> {
+    properties.get(name).map(|value| match value {
+        PropertyValue::StringValue(value) => value.as_str(),
+        _ => panic!("Property {name} should be a string, got {value:?}"),
+    })
+}
@@ -2,26 +2,24 @@ use std::collections::HashMap;
 
 use proc_macro2::TokenStream;
 use quote::quote;
-use tiled::Map;
 use util::{Number, ScrollStop};
 
+use crate::world::{level_objects, MapPart};
+
 const SCREEN_WIDTH: i32 = 240;
 const SCREEN_HEIGHT: i32 = 160;
 
 const SCROLL_BOX_SIZE: i32 = 128;
 
-pub fn get_scroll_stops(map: &Map) -> String {
-    let layer = map
-        .layers()
-        .filter(|x| x.name == "Scroll stops")
-        .find_map(|x| x.as_object_layer())
-        .unwrap();
-
-    let lines: Vec<_> = layer
-        .objects()
-        .flat_map(|x| match &x.shape {
+pub fn get_scroll_stops(parts: &[MapPart]) -> String {
+    let lines: Vec<_> = level_objects(parts, "Scroll stops")
+        .into_iter()
+        .flat_map(|(x, offset)| match &x.shape {
             tiled::ObjectShape::Polyline { points } => {
-                let points: Vec<_> = points.iter().map(|r| (r.0 + x.x, r.1 + x.y)).collect();
+                let points: Vec<_> = points
+                    .iter()
+                    .map(|r| (r.0 + x.x + offset.x, r.1 + x.y + offset.y))
+                    .collect();
                 let lines: Vec<_> = points
                     .windows(2)
                     .map(|x| {
@@ -122,13 +120,18 @@ pub fn get_scroll_stops(map: &Map) -> String {
     format!(
         "{}{}",
         quote! {
-            pub const SCROLL_STOP_BOX: i32 = #SCROLL_BOX_SIZE;
             pub static SCROLL_STOPS: phf::Map<[i32; 2], ScrollStop> =
         },
         phf.build()
     )
 }
 
+pub fn quote_box_size() -> TokenStream {
+    quote! {
+        pub const SCROLL_STOP_BOX: i32 = #SCROLL_BOX_SIZE;
+    }
+}
+
 fn optional_quote(a: Option<Number>) -> TokenStream {
     if let Some(a) = a {
         let a = a.to_raw();
@@ -0,0 +1,128 @@
+use nalgebra::Vector2;
+
+/// How far in pixels the simplified outline is allowed to be from the one drawn in Tiled
+pub const TOLERANCE: f32 = 0.5;
+
+/// Removes points which are (nearly) on the line between their neighbours, so that collinear
+/// segments become a single line and tiny corners don't each get their own arc.
+///
+/// Every point which is removed is within [`TOLERANCE`] of the simplified outline.
+pub fn simplify(points: &[(f32, f32)], is_polygon: bool) -> Vec<(f32, f32)> {
+    let mut points: Vec<_> = points.iter().map(|&(x, y)| Vector2::new(x, y)).collect();
+    points.dedup_by(|a, b| (*a - *b).norm() < f32::EPSILON);
+
+    if is_polygon {
+        while points.len() > 1 && (points[0] - points[points.len() - 1]).norm() < f32::EPSILON {
+            points.pop();
+        }
+    }
+
+    if points.len() < 3 {
+        return points.iter().map(|x| (x.x, x.y)).collect();
+    }
+
+    let simplified = if is_polygon {
+        // start at the top left most point since it must be a corner, and then split the polygon
+        // at the point furthest from it so each half can be simplified as a line
+        let start = (0..points.len())
+            .min_by(|&a, &b| {
+                (points[a].x, points[a].y)
+                    .partial_cmp(&(points[b].x, points[b].y))
+                    .unwrap()
+            })
+            .unwrap();
+        points.rotate_left(start);
+
+        let furthest = (1..points.len())
+            .max_by(|&a, &b| {
+                (points[a] - points[0])
+                    .norm()
+                    .total_cmp(&(points[b] - points[0]).norm())
+            })
+            .unwrap();
+
+        let mut ring = points.clone();
+        ring.push(points[0]);
+
+        let mut simplified = douglas_peucker(&ring[..=furthest]);
+        simplified.pop();
+        simplified.extend(douglas_peucker(&ring[furthest..]));
+        simplified.pop();
+
+        simplified
+    } else {
+        douglas_peucker(&points)
+    };
+
+    simplified.iter().map(|x| (x.x, x.y)).collect()
+}
+
+fn douglas_peucker(points: &[Vector2<f32>]) -> Vec<Vector2<f32>> {
+    let first = points[0];
+    let last = points[points.len() - 1];
+
+    let furthest = points[1..points.len() - 1]
+        .iter()
+        .enumerate()
+        .map(|(idx, &point)| (idx + 1, distance_to_segment(point, first, last)))
+        .max_by(|a, b| a.1.total_cmp(&b.1));
+
+    match furthest {
+        Some((idx, distance)) if distance > TOLERANCE => {
+            let mut simplified = douglas_peucker(&points[..=idx]);
+            simplified.pop();
+            simplified.extend(douglas_peucker(&points[idx..]));
+            simplified
+        }
+        _ => vec![first, last],
+    }
+}
+
+fn distance_to_segment(point: Vector2<f32>, start: Vector2<f32>, end: Vector2<f32>) -> f32 {
+    let direction = end - start;
+    let length_squared = direction.norm_squared();
+
+    if length_squared < f32::EPSILON {
+        return (point - start).norm();
+    }
+
+    let t = ((point - start).dot(&direction) / length_squared).clamp(0., 1.);
+    (point - (start + direction * t)).norm()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merges_collinear_segments() {
+        let points = [(0., 0.), (10., 0.), (20., 0.), (30., 0.1)];
+
+        assert_eq!(simplify(&points, false), vec![(0., 0.), (30., 0.1)]);
+    }
+
+    #[test]
+    fn keeps_corners_bigger_than_the_tolerance() {
+        let points = [(0., 0.), (10., 1.), (20., 0.)];
+
+        assert_eq!(simplify(&points, false), points.to_vec());
+    }
+
+    #[test]
+    fn polygon_keeps_its_corners() {
+        let points = [
+            (0., 0.),
+            (5., 0.),
+            (10., 0.),
+            (10., 10.),
+            (10., 10.),
+            (0., 10.),
+            (0., 5.),
+        ];
+
+        assert_eq!(
+            simplify(&points, true),
+            vec![(0., 0.), (10., 0.), (10., 10.), (0., 10.)]
+        );
+    }
+}
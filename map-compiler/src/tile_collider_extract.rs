@@ -0,0 +1,431 @@
+use std::collections::{HashMap, HashSet};
+
+use nalgebra::Vector2;
+use tiled::{ObjectData, ObjectShape};
+use util::ColliderTag;
+
+use crate::world::MapPart;
+
+/// Collision shapes are snapped to this fraction of a pixel so that edges of neighbouring tiles
+/// can be matched up exactly
+const SUBPIXELS: f32 = 16.;
+
+type Point = (i32, i32);
+
+/// A collision outline built from the collision shapes of tiles
+pub struct Outline {
+    pub points: Vec<Vector2<f32>>,
+    pub is_polygon: bool,
+    pub tag: ColliderTag,
+}
+
+/// Places the collision shapes defined on tiles in the tileset wherever those tiles are used,
+/// removing the edges between neighbouring tiles and joining the rest into outlines. Tiles from
+/// every part of the level are joined up, so outlines carry on across the edges between maps.
+pub fn extract_tile_outlines(parts: &[MapPart]) -> Vec<Outline> {
+    let mut edges: Vec<(ColliderTag, HashSet<(Point, Point)>)> = Vec::new();
+    let mut outlines = Vec::new();
+
+    for (part, (layer, infinite_layer)) in parts
+        .iter()
+        .flat_map(|part| part.tile_layers().map(move |layer| (part, layer)))
+    {
+        let map = part.map;
+        let tile_size = (map.tile_width as f32, map.tile_height as f32);
+        let layer_offset = part.layer_offset(&layer);
+
+        for ((chunk_x, chunk_y), chunk) in infinite_layer.chunks() {
+            for y in 0..tiled::ChunkData::HEIGHT as i32 {
+                for x in 0..tiled::ChunkData::WIDTH as i32 {
+                    let Some(layer_tile) = chunk.get_tile(x, y) else {
+                        continue;
+                    };
+                    let Some(collision) = layer_tile
+                        .get_tile()
+                        .and_then(|tile| tile.collision.clone())
+                    else {
+                        continue;
+                    };
+
+                    let tileset = layer_tile.get_tileset();
+                    assert!(
+                        tileset.tile_width == map.tile_width
+                            && tileset.tile_height == map.tile_height,
+                        "Tiles in {} with collision shapes should be the same size as the map's tiles",
+                        tileset.name
+                    );
+
+                    let tile_position = layer_offset
+                        + Vector2::new(
+                            ((chunk_x * tiled::ChunkData::WIDTH as i32 + x) as f32) * tile_size.0,
+                            ((chunk_y * tiled::ChunkData::HEIGHT as i32 + y) as f32) * tile_size.1,
+                        );
+
+                    assert!(
+                        !layer_tile.flip_d || tile_size.0 == tile_size.1,
+                        "Only square tiles with collision shapes can be rotated, {} aren't",
+                        tileset.name
+                    );
+
+                    // Tiled swaps the axes of diagonally flipped tiles before flipping them
+                    // horizontally and vertically, which together make up rotations
+                    let place = |(point_x, point_y): (f32, f32)| {
+                        let (point_x, point_y) = if layer_tile.flip_d {
+                            (point_y, point_x)
+                        } else {
+                            (point_x, point_y)
+                        };
+                        let point_x = if layer_tile.flip_h {
+                            tile_size.0 - point_x
+                        } else {
+                            point_x
+                        };
+                        let point_y = if layer_tile.flip_v {
+                            tile_size.1 - point_y
+                        } else {
+                            point_y
+                        };
+
+                        tile_position + Vector2::new(point_x, point_y)
+                    };
+
+                    for object in collision.object_data() {
+                        let tag = collision_tag(object);
+                        let (points, is_polygon) = object_points(object);
+                        let points: Vec<_> = points.into_iter().map(place).collect();
+
+                        if !is_polygon {
+                            outlines.push(Outline {
+                                points,
+                                is_polygon,
+                                tag,
+                            });
+                            continue;
+                        }
+
+                        let mut points: Vec<_> = points.into_iter().map(snap).collect();
+                        // flipping a tile flips the winding of its shapes too
+                        if signed_area(&points) < 0 {
+                            points.reverse();
+                        }
+
+                        let tag_edges = match edges.iter_mut().find(|(x, _)| *x == tag) {
+                            Some((_, tag_edges)) => tag_edges,
+                            None => {
+                                edges.push((tag, HashSet::new()));
+                                &mut edges.last_mut().unwrap().1
+                            }
+                        };
+
+                        for (&start, &end) in points.iter().zip(points.iter().cycle().skip(1)) {
+                            if start != end {
+                                tag_edges.insert((start, end));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    for (tag, tag_edges) in edges {
+        outlines.extend(join_edges(tag_edges).into_iter().map(|points| {
+            Outline {
+                points: points
+                    .into_iter()
+                    .map(|(x, y)| Vector2::new(x as f32, y as f32) / SUBPIXELS)
+                    .collect(),
+                is_polygon: true,
+                tag,
+            }
+        }));
+    }
+
+    outlines
+}
+
+/// The class of a tile's collision object says which layer it would have been drawn on
+fn collision_tag(object: &ObjectData) -> ColliderTag {
+    match object.user_type.as_str() {
+        "" | "Colliders" => ColliderTag::CollisionGravitational,
+        "Colliders No Gravity" => ColliderTag::CollisionOnly,
+        "Killision" => ColliderTag::Killision,
+        class => panic!("Unknown class '{class}' for tile collision shape"),
+    }
+}
+
+fn object_points(object: &ObjectData) -> (Vec<(f32, f32)>, bool) {
+    let offset = |points: &[(f32, f32)]| {
+        points
+            .iter()
+            .map(|(x, y)| (x + object.x, y + object.y))
+            .collect()
+    };
+
+    match &object.shape {
+        ObjectShape::Rect { width, height } => (
+            offset(&[(0., 0.), (*width, 0.), (*width, *height), (0., *height)]),
+            true,
+        ),
+        ObjectShape::Polygon { points } => (offset(points), true),
+        ObjectShape::Polyline { points } => (offset(points), false),
+        shape => {
+            panic!(
+                "Tile collision shapes should be rectangles, polygons or polylines, got {shape:?}"
+            )
+        }
+    }
+}
+
+fn snap(point: Vector2<f32>) -> Point {
+    (
+        (point.x * SUBPIXELS).round() as i32,
+        (point.y * SUBPIXELS).round() as i32,
+    )
+}
+
+/// Positive when the points go clockwise on screen, which makes the normals face outwards
+fn signed_area(points: &[Point]) -> i64 {
+    points
+        .iter()
+        .zip(points.iter().cycle().skip(1))
+        .map(|(a, b)| a.0 as i64 * b.1 as i64 - b.0 as i64 * a.1 as i64)
+        .sum()
+}
+
+/// Removes the edges which are shared by two shapes, since they are inside the combined shape, and
+/// then follows the remaining edges around to make closed outlines.
+fn join_edges(edges: HashSet<(Point, Point)>) -> Vec<Vec<Point>> {
+    let edges = split_at_vertices(edges);
+
+    let mut outgoing: HashMap<Point, Vec<Point>> = HashMap::new();
+    for &(start, end) in &edges {
+        if !edges.contains(&(end, start)) {
+            outgoing.entry(start).or_default().push(end);
+        }
+    }
+
+    // so that the output doesn't depend on the hash set's ordering
+    for ends in outgoing.values_mut() {
+        ends.sort();
+    }
+    let mut starts: Vec<_> = outgoing.keys().copied().collect();
+    starts.sort();
+
+    let mut outlines = Vec::new();
+
+    for start in starts {
+        while let Some(mut end) = outgoing.get_mut(&start).and_then(|x| x.pop()) {
+            let mut outline = vec![start];
+
+            while end != start {
+                outline.push(end);
+                end = outgoing
+                    .get_mut(&end)
+                    .and_then(|x| x.pop())
+                    .expect("Every point in a closed outline should have an edge leaving it");
+            }
+
+            outlines.push(remove_collinear_points(outline));
+        }
+    }
+
+    outlines
+}
+
+/// Splits edges wherever another shape's corner lies along them, so that edges shared by shapes
+/// which meet at different points (like a half height tile next to a full one) still cancel out
+fn split_at_vertices(edges: HashSet<(Point, Point)>) -> HashSet<(Point, Point)> {
+    // edges are about a tile long, so only the corners near each one need checking
+    const CELL: i32 = 8 * SUBPIXELS as i32;
+    let cell = |point: Point| (point.0.div_floor(CELL), point.1.div_floor(CELL));
+
+    let mut vertices: HashMap<(i32, i32), HashSet<Point>> = HashMap::new();
+    for &(start, end) in &edges {
+        vertices.entry(cell(start)).or_default().insert(start);
+        vertices.entry(cell(end)).or_default().insert(end);
+    }
+
+    let mut split = HashSet::new();
+
+    for (start, end) in edges {
+        let direction = (end.0 as i64 - start.0 as i64, end.1 as i64 - start.1 as i64);
+        let length_squared = direction.0 * direction.0 + direction.1 * direction.1;
+
+        let (start_cell, end_cell) = (cell(start), cell(end));
+        let cells_x = start_cell.0.min(end_cell.0)..=start_cell.0.max(end_cell.0);
+        let cells_y = start_cell.1.min(end_cell.1)..=start_cell.1.max(end_cell.1);
+
+        // how far along the edge each corner which lies strictly inside it is
+        let mut along: Vec<(i64, Point)> = cells_x
+            .flat_map(|x| cells_y.clone().map(move |y| (x, y)))
+            .filter_map(|cell| vertices.get(&cell))
+            .flatten()
+            .filter_map(|&point| {
+                let offset = (
+                    point.0 as i64 - start.0 as i64,
+                    point.1 as i64 - start.1 as i64,
+                );
+                let cross = direction.0 * offset.1 - direction.1 * offset.0;
+                let dot = direction.0 * offset.0 + direction.1 * offset.1;
+
+                (cross == 0 && dot > 0 && dot < length_squared).then_some((dot, point))
+            })
+            .collect();
+        along.sort();
+
+        let mut from = start;
+        for (_, point) in along {
+            split.insert((from, point));
+            from = point;
+        }
+        split.insert((from, end));
+    }
+
+    split
+}
+
+fn remove_collinear_points(mut points: Vec<Point>) -> Vec<Point> {
+    let mut idx = 0;
+
+    while idx < points.len() && points.len() > 3 {
+        let a = points[(idx + points.len() - 1) % points.len()];
+        let o = points[idx];
+        let b = points[(idx + 1) % points.len()];
+
+        let ao = (o.0 as i64 - a.0 as i64, o.1 as i64 - a.1 as i64);
+        let ob = (b.0 as i64 - o.0 as i64, b.1 as i64 - o.1 as i64);
+
+        if ao.0 * ob.1 - ao.1 * ob.0 == 0 && ao.0 * ob.0 + ao.1 * ob.1 > 0 {
+            points.remove(idx);
+            // the previous point might now be collinear with its new neighbour
+            idx = idx.saturating_sub(1);
+        } else {
+            idx += 1;
+        }
+    }
+
+    points
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::load_fixture;
+
+    fn square(x: i32, y: i32) -> Vec<Point> {
+        vec![(x, y), (x + 8, y), (x + 8, y + 8), (x, y + 8)]
+    }
+
+    fn edges_of(shapes: &[Vec<Point>]) -> HashSet<(Point, Point)> {
+        shapes
+            .iter()
+            .flat_map(|points| {
+                points
+                    .iter()
+                    .copied()
+                    .zip(points.iter().copied().cycle().skip(1))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn neighbouring_tiles_make_one_outline() {
+        let outlines = join_edges(edges_of(&[square(0, 0), square(8, 0), square(16, 0)]));
+
+        assert_eq!(outlines.len(), 1);
+
+        let mut points = outlines[0].clone();
+        points.sort();
+        assert_eq!(points, vec![(0, 0), (0, 8), (24, 0), (24, 8)]);
+    }
+
+    #[test]
+    fn separate_tiles_stay_separate() {
+        let outlines = join_edges(edges_of(&[square(0, 0), square(16, 0)]));
+
+        assert_eq!(outlines.len(), 2);
+        assert!(outlines.iter().all(|outline| outline.len() == 4));
+    }
+
+    #[test]
+    fn edges_split_at_different_points_still_cancel() {
+        // a half height tile next to a full one, sharing the bottom half of an edge
+        let half = vec![(8, 4), (16, 4), (16, 8), (8, 8)];
+        let outlines = join_edges(edges_of(&[square(0, 0), half]));
+
+        assert_eq!(outlines.len(), 1);
+
+        let mut points = outlines[0].clone();
+        points.sort();
+        assert_eq!(
+            points,
+            vec![(0, 0), (0, 8), (8, 0), (8, 4), (16, 4), (16, 8)]
+        );
+    }
+
+    #[test]
+    fn outlines_go_clockwise() {
+        assert!(signed_area(&square(0, 0)) > 0);
+
+        let outlines = join_edges(edges_of(&[square(0, 0), square(0, 8)]));
+        assert!(signed_area(&outlines[0]) > 0);
+    }
+
+    /// The points of the outline in whole pixels, sorted so that outlines can be compared
+    /// wherever they start
+    fn sorted_points(outline: &Outline) -> Vec<(i32, i32)> {
+        let mut points: Vec<_> = outline
+            .points
+            .iter()
+            .map(|point| (point.x as i32, point.y as i32))
+            .collect();
+        points.sort();
+        points
+    }
+
+    #[test]
+    fn tile_shapes_are_placed_flipped_and_tagged() {
+        let map = load_fixture("tile-shapes.tmx");
+        let parts = [MapPart {
+            map: &map,
+            index: 0,
+            offset: Vector2::zeros(),
+            include_hidden: false,
+        }];
+
+        let outlines = extract_tile_outlines(&parts);
+        let outlines_tagged = |tag: ColliderTag| {
+            let mut outlines: Vec<_> = outlines
+                .iter()
+                .filter(|outline| outline.tag == tag)
+                .map(|outline| (outline.is_polygon, sorted_points(outline)))
+                .collect();
+            outlines.sort();
+            outlines
+        };
+
+        // the layer is offset by (16, 8), which moves every shape with it
+        assert_eq!(
+            outlines_tagged(ColliderTag::CollisionGravitational),
+            vec![
+                // a slope as it is in the tileset
+                (true, vec![(16, 8), (16, 16), (24, 16)]),
+                // flipped horizontally
+                (true, vec![(32, 16), (40, 8), (40, 16)]),
+                // flipped diagonally
+                (true, vec![(48, 8), (56, 8), (56, 16)]),
+                // two squares next to each other, joined into one outline
+                (true, vec![(64, 8), (64, 16), (80, 8), (80, 16)]),
+            ]
+        );
+        assert_eq!(
+            outlines_tagged(ColliderTag::Killision),
+            vec![(false, vec![(88, 10), (96, 10)])]
+        );
+        assert_eq!(
+            outlines_tagged(ColliderTag::CollisionOnly),
+            vec![(true, vec![(104, 12), (104, 16), (112, 12), (112, 16)])]
+        );
+    }
+}
@@ -0,0 +1,223 @@
+use std::{collections::BTreeSet, path::PathBuf};
+
+use proc_macro2::{Ident, TokenStream};
+use quote::{format_ident, quote};
+use tiled::Map;
+
+struct RegisteredTileset {
+    /// Index of the map, and of the tileset in that map's list of tilesets, for every level
+    /// which uses this tileset
+    used_as: Vec<(usize, usize)>,
+    variant: Ident,
+    module: Ident,
+    path: String,
+    /// Tile id and its (tile id, duration in frames) animation frames
+    animations: Vec<(u16, Vec<(u16, u16)>)>,
+}
+
+/// The tilesets which are used by tile layers in any of the levels, and so need to be included
+/// in the game
+pub struct TilesetRegistry {
+    tilesets: Vec<RegisteredTileset>,
+}
+
+impl TilesetRegistry {
+    /// Takes every map along with the indices of the tilesets it uses
+    pub fn new(maps: &[(&Map, BTreeSet<usize>)]) -> Self {
+        let mut tilesets: Vec<RegisteredTileset> = Vec::new();
+
+        for (map_index, (map, used_tilesets)) in maps.iter().enumerate() {
+            for &index in used_tilesets {
+                let tileset = &map.tilesets()[index];
+                let image = tileset
+                    .image
+                    .as_ref()
+                    .unwrap_or_else(|| panic!("Tileset {} should be a single image", tileset.name));
+                let path = image_path(image.source.clone());
+
+                // levels can share tilesets, which only need including once
+                if let Some(registered) = tilesets.iter_mut().find(|x| x.path == path) {
+                    registered.used_as.push((map_index, index));
+                    continue;
+                }
+
+                let mut animations: Vec<_> = tileset
+                    .tiles()
+                    .filter_map(|(tile_id, tile)| {
+                        let frames = tile
+                            .animation
+                            .as_ref()?
+                            .iter()
+                            .map(|frame| (frame.tile_id as u16, ms_to_frames(frame.duration)))
+                            .collect();
+
+                        Some((tile_id as u16, frames))
+                    })
+                    .collect();
+                animations.sort();
+
+                let variant = format_ident!("{}", camel_case(&tileset.name));
+                assert!(
+                    tilesets.iter().all(|x| x.variant != variant),
+                    "Two different tilesets are called {}",
+                    tileset.name
+                );
+
+                tilesets.push(RegisteredTileset {
+                    used_as: vec![(map_index, index)],
+                    variant,
+                    module: format_ident!("{}", snake_case(&tileset.name)),
+                    path,
+                    animations,
+                });
+            }
+        }
+
+        Self { tilesets }
+    }
+
+    pub fn variant(&self, map_index: usize, index: usize) -> TokenStream {
+        let tileset = self
+            .tilesets
+            .iter()
+            .find(|x| x.used_as.contains(&(map_index, index)))
+            .expect("Tileset should have been registered");
+        let variant = &tileset.variant;
+
+        quote!(super::MapTileSet::#variant)
+    }
+
+    pub fn quote(&self) -> TokenStream {
+        let variants: Vec<_> = self.tilesets.iter().map(|x| &x.variant).collect();
+        let modules = self.tilesets.iter().map(|x| &x.module);
+        let paths = self.tilesets.iter().map(|x| &x.path);
+        let blank_tileset = variants
+            .first()
+            .expect("Map should use at least one tileset");
+
+        let animated_tilesets: Vec<_> = self
+            .tilesets
+            .iter()
+            .filter(|x| !x.animations.is_empty())
+            .collect();
+        let animated_variants = animated_tilesets.iter().map(|x| &x.variant);
+        let animated_modules = animated_tilesets
+            .iter()
+            .map(|x| format_ident!("{}_animated", x.module));
+        let animated_paths = animated_tilesets.iter().map(|x| &x.path);
+
+        let animations = self.tilesets.iter().flat_map(|tileset| {
+            let variant = &tileset.variant;
+            tileset.animations.iter().map(move |(tile_id, frames)| {
+                let frames = frames.iter().map(|(tile_id, duration)| {
+                    quote! {
+                        super::AnimationFrame {
+                            tile_id: #tile_id,
+                            duration: #duration,
+                        }
+                    }
+                });
+
+                quote! {
+                    super::TileAnimation {
+                        map_tile_set: MapTileSet::#variant,
+                        tile_id: #tile_id,
+                        frames: &[#(#frames),*],
+                    }
+                }
+            })
+        });
+
+        quote! {
+            #[derive(Clone, Copy, PartialEq, Eq, Debug)]
+            pub enum MapTileSet {
+                #(#variants),*
+            }
+
+            pub const BLANK_TILE: super::MapTileSetting = super::MapTileSetting {
+                tile_id: (1 << 10) - 1,
+                hflip: false,
+                vflip: false,
+                map_tile_set: MapTileSet::#blank_tileset,
+                animated: false,
+            };
+
+            pub static TILE_ANIMATIONS: &[super::TileAnimation] = &[#(#animations),*];
+
+            /// Calls the given macro with `Variant module => "path"` for every tileset used by the
+            /// map, followed by the tilesets which have animated tiles, so the game can include
+            /// the graphics for them
+            #[macro_export]
+            macro_rules! with_map_tilesets {
+                ($callback:ident) => {
+                    $callback!(
+                        [#(#variants #modules => #paths),*]
+                        [#(#animated_variants #animated_modules => #animated_paths),*]
+                    );
+                };
+            }
+        }
+    }
+}
+
+/// Tiled stores animation frame durations in milliseconds, and the GBA runs at ~60fps
+fn ms_to_frames(duration: u32) -> u16 {
+    ((duration * 60 + 500) / 1000).max(1) as u16
+}
+
+/// Prefers the aseprite file the image was exported from, since that is what the artists edit
+fn image_path(source: PathBuf) -> String {
+    let source = source
+        .canonicalize()
+        .unwrap_or_else(|err| panic!("Could not find tileset image {source:?}, {err}"));
+    let aseprite = source.with_extension("aseprite");
+
+    let path = if aseprite.exists() { aseprite } else { source };
+    path.to_str()
+        .expect("Path should be valid utf-8")
+        .to_string()
+}
+
+fn words(name: &str) -> impl Iterator<Item = &str> {
+    name.split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|x| !x.is_empty())
+}
+
+fn camel_case(name: &str) -> String {
+    words(name)
+        .map(|word| {
+            let mut chars = word.chars();
+            chars
+                .next()
+                .map(|first| first.to_ascii_uppercase().to_string() + chars.as_str())
+                .unwrap_or_default()
+        })
+        .collect()
+}
+
+fn snake_case(name: &str) -> String {
+    words(name)
+        .map(|word| word.to_ascii_lowercase())
+        .collect::<Vec<_>>()
+        .join("_")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_tileset_names() {
+        assert_eq!(camel_case("planets2"), "Planets2");
+        assert_eq!(camel_case("space station-tiles"), "SpaceStationTiles");
+        assert_eq!(snake_case("planets2"), "planets2");
+        assert_eq!(snake_case("Space Station-tiles"), "space_station_tiles");
+    }
+
+    #[test]
+    fn converts_animation_durations_to_frames() {
+        assert_eq!(ms_to_frames(1000), 60);
+        assert_eq!(ms_to_frames(100), 6);
+        assert_eq!(ms_to_frames(1), 1);
+    }
+}
@@ -0,0 +1,249 @@
+use std::{
+    error::Error,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use nalgebra::Vector2;
+use serde::Deserialize;
+use tiled::{InfiniteTileLayer, Layer, Map, Object};
+
+use crate::game_tile_layers;
+
+/// The parts of a Tiled `.world` file which are needed to place its maps
+#[derive(Deserialize)]
+struct WorldFile {
+    maps: Vec<WorldMap>,
+    #[serde(default)]
+    patterns: Vec<serde_json::Value>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct WorldMap {
+    /// Relative to the world file
+    file_name: String,
+    x: i32,
+    y: i32,
+}
+
+/// A map file and where its origin goes in the level
+type MapPlacement = (PathBuf, Vector2<f32>);
+
+/// The maps which make up a level along with where each one goes in the level. This is every map
+/// in a `.world` file, or just the map itself for a `.tmx` file.
+pub fn level_maps(path: &Path) -> Result<Vec<MapPlacement>, Box<dyn Error>> {
+    if path
+        .extension()
+        .is_none_or(|extension| extension != "world")
+    {
+        return Ok(vec![(path.to_path_buf(), Vector2::zeros())]);
+    }
+
+    let directory = path.parent().unwrap_or(Path::new(""));
+    let maps = world_maps(&fs::read_to_string(path)?, directory)?;
+    assert!(
+        !maps.is_empty(),
+        "World {} should have at least one map",
+        path.display()
+    );
+
+    Ok(maps)
+}
+
+fn world_maps(json: &str, directory: &Path) -> Result<Vec<MapPlacement>, serde_json::Error> {
+    let world: WorldFile = serde_json::from_str(json)?;
+    assert!(
+        world.patterns.is_empty(),
+        "Worlds with patterns aren't supported, list each map instead"
+    );
+
+    Ok(world
+        .maps
+        .into_iter()
+        .map(|map| {
+            (
+                directory.join(map.file_name),
+                Vector2::new(map.x as f32, map.y as f32),
+            )
+        })
+        .collect())
+}
+
+/// One of the maps which make up a level, and where its origin is in the level
+#[derive(Clone, Copy)]
+pub struct MapPart<'a> {
+    pub map: &'a Map,
+    /// Index of the map in the list of every map being compiled
+    pub index: usize,
+    pub offset: Vector2<f32>,
+    /// Whether layers and objects which are hidden in Tiled are compiled anyway
+    pub include_hidden: bool,
+}
+
+impl<'a> MapPart<'a> {
+    /// Hidden layers and objects are left out of the game, like they are in Tiled
+    pub fn is_shown(&self, visible: bool) -> bool {
+        visible || self.include_hidden
+    }
+
+    /// Where the origin of the layer is in the level, taking into account its offset in Tiled
+    pub fn layer_offset(&self, layer: &Layer) -> Vector2<f32> {
+        self.offset + Vector2::new(layer.offset_x, layer.offset_y)
+    }
+
+    /// The offset of the layer in tiles, which its tiles need so that they line up with the other
+    /// layers and maps
+    pub fn tile_offset(&self, layer: &Layer) -> (i32, i32) {
+        let offset = self.layer_offset(layer);
+        let tile_size = (self.map.tile_width as f32, self.map.tile_height as f32);
+
+        assert!(
+            offset.x % tile_size.0 == 0. && offset.y % tile_size.1 == 0.,
+            "Tile layer '{}' should be offset by whole tiles, but is at ({}, {})",
+            layer.name,
+            offset.x,
+            offset.y
+        );
+
+        (
+            (offset.x / tile_size.0) as i32,
+            (offset.y / tile_size.1) as i32,
+        )
+    }
+
+    /// The tile layers of this part which end up in the game
+    pub fn tile_layers(&self) -> impl Iterator<Item = (Layer<'a>, InfiniteTileLayer<'a>)> {
+        game_tile_layers(self.map, self.include_hidden)
+    }
+}
+
+/// The objects on every object layer called `layer_name` in any of the parts, along with the
+/// offset which places each of them in the level. Parts don't need to have the layer at all.
+pub fn level_objects<'a>(
+    parts: &[MapPart<'a>],
+    layer_name: &str,
+) -> Vec<(Object<'a>, Vector2<f32>)> {
+    parts
+        .iter()
+        .flat_map(|part| {
+            part.map
+                .layers()
+                .filter(|layer| layer.name == layer_name && part.is_shown(layer.visible))
+                .filter_map(|layer| {
+                    let offset = part.layer_offset(&layer);
+                    layer.as_object_layer().map(|layer| (layer, offset))
+                })
+                .flat_map(|(layer, offset)| {
+                    layer
+                        .objects()
+                        .filter(|object| part.is_shown(object.visible))
+                        .map(|object| (object, offset))
+                        .collect::<Vec<_>>()
+                })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::load_fixture;
+
+    fn part(map: &Map, include_hidden: bool) -> MapPart<'_> {
+        MapPart {
+            map,
+            index: 0,
+            offset: Vector2::new(240., -160.),
+            include_hidden,
+        }
+    }
+
+    fn layer<'a>(map: &'a Map, name: &str) -> Layer<'a> {
+        map.layers().find(|layer| layer.name == name).unwrap()
+    }
+
+    #[test]
+    fn maps_are_relative_to_the_world() {
+        let json = r#"{
+            "maps": [
+                { "fileName": "start.tmx", "x": 0, "y": 0, "width": 240, "height": 160 },
+                { "fileName": "caves/deep.tmx", "x": 240, "y": -160, "width": 480, "height": 160 }
+            ],
+            "onlyShowAdjacentMaps": false,
+            "type": "world"
+        }"#;
+
+        let maps = world_maps(json, Path::new("levels")).unwrap();
+
+        assert_eq!(
+            maps,
+            vec![
+                (PathBuf::from("levels/start.tmx"), Vector2::new(0., 0.)),
+                (
+                    PathBuf::from("levels/caves/deep.tmx"),
+                    Vector2::new(240., -160.)
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn hidden_layers_and_objects_are_left_out() {
+        let map = load_fixture("hidden.tmx");
+
+        let objects = level_objects(&[part(&map, false)], "Things");
+        let ids: Vec<_> = objects.iter().map(|(object, _)| object.id()).collect();
+        assert_eq!(ids, [1]);
+
+        let layers: Vec<_> = part(&map, false)
+            .tile_layers()
+            .map(|(layer, _)| layer.name.clone())
+            .collect();
+        assert_eq!(layers, ["Ground", "Nudged"]);
+    }
+
+    #[test]
+    fn hidden_layers_and_objects_are_kept_when_including_hidden() {
+        let map = load_fixture("hidden.tmx");
+
+        let objects = level_objects(&[part(&map, true)], "Things");
+        let ids: Vec<_> = objects.iter().map(|(object, _)| object.id()).collect();
+        assert_eq!(ids, [1, 2, 3]);
+
+        let layers: Vec<_> = part(&map, true)
+            .tile_layers()
+            .map(|(layer, _)| layer.name.clone())
+            .collect();
+        assert_eq!(layers, ["Ground", "Hidden", "Nudged"]);
+    }
+
+    #[test]
+    fn layer_offsets_are_added_to_where_the_map_is() {
+        let map = load_fixture("hidden.tmx");
+
+        let objects = level_objects(&[part(&map, true)], "Things");
+        let offsets: Vec<_> = objects.iter().map(|(_, offset)| *offset).collect();
+        assert_eq!(
+            offsets,
+            [
+                Vector2::new(248., -144.),
+                Vector2::new(248., -144.),
+                Vector2::new(240., -160.),
+            ]
+        );
+
+        assert_eq!(
+            part(&map, false).tile_offset(&layer(&map, "Ground")),
+            (32, -19)
+        );
+    }
+
+    #[test]
+    #[should_panic = "Tile layer 'Nudged' should be offset by whole tiles, but is at (244, -160)"]
+    fn tile_layers_have_to_be_offset_by_whole_tiles() {
+        let map = load_fixture("hidden.tmx");
+
+        part(&map, false).tile_offset(&layer(&map, "Nudged"));
+    }
+}
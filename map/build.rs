@@ -1,10 +1,18 @@
 use std::io::Write;
 use std::{error::Error, fs::File, io::BufWriter};
 
+use map_compiler::CompileOptions;
+
+/// The name of each level, which the game can find it by, and the map or `.world` it is made from
+const LEVELS: &[(&str, &str)] = &[("main", "map.tmx")];
+
 fn main() -> Result<(), Box<dyn Error>> {
     let out_dir = std::env::var("OUT_DIR").expect("OUT_DIR environment variable must be specified");
 
-    let map = map_compiler::compile_map("map.tmx")?;
+    let options = CompileOptions {
+        include_hidden: std::env::var_os("CARGO_FEATURE_INCLUDE_HIDDEN").is_some(),
+    };
+    let map = map_compiler::compile_levels(LEVELS, options)?;
 
     let output_file = File::create(format!("{out_dir}/map.rs"))?;
     let mut writer = BufWriter::new(output_file);
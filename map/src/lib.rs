@@ -1,8 +1,10 @@
 #![no_std]
 #![feature(int_roundings)]
 use agb_fixnum::{Num, Vector2D};
-use util::{Collider, Number, ScrollStop};
+use util::{Collider, Number, ScrollSpring, ScrollStop};
 
+// the names of the moving objects' images come from Tiled, and are used as they are
+#[allow(non_camel_case_types)]
 mod map {
     use super::*;
     use agb_fixnum::{Num, Vector2D};
@@ -13,55 +15,206 @@ mod map {
 
 pub struct PathPoint {
     pub point: Vector2D<Number>,
+    /// How much of the segment starting at this point is covered each frame
     pub incrementer: Num<i32, 24>,
+    /// Number of frames to wait on arriving at this point
+    pub pause: u16,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PathDirection {
+    Forwards,
+    Backwards,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PathEasing {
+    Linear,
+    EaseIn,
+    EaseOut,
+    EaseInOut,
+}
+
+impl PathEasing {
+    /// Maps the linear progress along a segment (between 0 and 1) to the eased progress
+    pub fn apply(self, t: Num<i32, 24>) -> Num<i32, 24> {
+        let one = Num::new(1);
+        match self {
+            PathEasing::Linear => t,
+            PathEasing::EaseIn => t * t,
+            PathEasing::EaseOut => one - (one - t) * (one - t),
+            PathEasing::EaseInOut => t * t * (Num::new(3) - t * 2),
+        }
+    }
+}
+
+/// Where along the path a moving body should be when it is first loaded
+#[derive(Clone, Copy, Debug)]
+pub struct PathStart {
+    pub index: usize,
+    pub direction: PathDirection,
+    pub timer: Num<i32, 24>,
+    pub pause: u16,
+    /// A one-shot path which had already reached its end
+    pub finished: bool,
+}
+
+pub struct BodyImage {
+    pub image: DynamicColliderImage,
+    /// Offset of the centre of the image from the body's position on the path
+    pub offset: Vector2D<Number>,
+}
+
+/// A set of colliders and images which move along a path together
+pub struct Body {
+    pub colliders: &'static [Collider],
+    pub images: &'static [BodyImage],
+    pub start: PathStart,
 }
 
 pub struct Path {
     pub points: &'static [PathPoint],
-    pub colliders: &'static [Collider],
+    pub bodies: &'static [Body],
     pub complete: bool,
-    pub image: DynamicColliderImage,
+    pub one_shot: bool,
+    pub easing: PathEasing,
 }
 
-pub use map::{DynamicColliderImage, CAMERA_START, START_POINT};
+pub use map::DynamicColliderImage;
 
-pub fn get_recovery_point(position: Vector2D<Number>) -> Vector2D<Number> {
-    map::RECOVERY_POINTS
-        .iter()
-        .copied()
-        .min_by_key(|&x| (x - position).magnitude_squared())
-        .unwrap()
+/// Somewhere in a level which takes the player to another level
+pub struct LevelExit {
+    pub point: Vector2D<i32>,
+    /// How close the player needs to be to leave the level
+    pub radius: i32,
+    /// The name of the level to go to
+    pub level: &'static str,
 }
 
-pub fn get_paths(x: i32, y: i32) -> &'static [&'static Path] {
-    let x = x.div_floor(map::PATH_BOX_SIZE);
-    let y = y.div_floor(map::PATH_BOX_SIZE);
-
-    map::PATH_LOOKUP.get(&[x, y]).copied().unwrap_or_default()
+/// Where a time attack run through the level ends
+pub struct Finish {
+    pub point: Vector2D<i32>,
+    /// How close the player needs to be to finish
+    pub radius: i32,
 }
 
-pub fn get_nearby(x: i32, y: i32) -> &'static [&'static Collider] {
-    let x = x.div_floor(map::BOX_SIZE);
-    let y = y.div_floor(map::BOX_SIZE);
+impl Finish {
+    pub fn is_reached(&self, position: Vector2D<i32>) -> bool {
+        (self.point - position).magnitude_squared() < self.radius * self.radius
+    }
+}
 
-    map::NEARBY_COLLIDERS
-        .get(&[x, y])
-        .copied()
-        .unwrap_or_default()
+pub struct Level {
+    pub name: &'static str,
+    pub start_point: Vector2D<Number>,
+    pub camera_start: Vector2D<Number>,
+    pub tile_layers: &'static [TileLayer],
+    pub power_ups: &'static [PowerUp],
+    pub mission_logs: &'static [MissionLog],
+    pub exits: &'static [LevelExit],
+    pub finish: Option<Finish>,
+    recovery_points: &'static [Vector2D<Number>],
+    nearby_colliders: &'static phf::Map<[i32; 2], &'static [&'static Collider]>,
+    path_lookup: &'static phf::Map<[i32; 2], &'static [&'static Path]>,
+    scroll_stops: &'static phf::Map<[i32; 2], ScrollStop>,
+    camera_zones: &'static phf::Map<[i32; 2], &'static [&'static CameraZone]>,
 }
 
-pub fn get_scroll_stop(x: i32, y: i32) -> Option<&'static ScrollStop> {
-    let x = x.div_floor(map::SCROLL_STOP_BOX);
-    let y = y.div_floor(map::SCROLL_STOP_BOX);
+pub use map::LEVELS;
 
-    map::SCROLL_STOPS.get(&[x, y])
+impl Level {
+    pub fn by_name(name: &str) -> Option<&'static Level> {
+        LEVELS.iter().copied().find(|level| level.name == name)
+    }
+
+    pub fn get_recovery_point(&self, position: Vector2D<Number>) -> Vector2D<Number> {
+        self.recovery_points
+            .iter()
+            .copied()
+            .min_by_key(|&x| (x - position).magnitude_squared())
+            .unwrap()
+    }
+
+    pub fn get_paths(&self, x: i32, y: i32) -> &'static [&'static Path] {
+        let x = x.div_floor(map::PATH_BOX_SIZE);
+        let y = y.div_floor(map::PATH_BOX_SIZE);
+
+        self.path_lookup.get(&[x, y]).copied().unwrap_or_default()
+    }
+
+    pub fn get_nearby(&self, x: i32, y: i32) -> &'static [&'static Collider] {
+        let x = x.div_floor(map::BOX_SIZE);
+        let y = y.div_floor(map::BOX_SIZE);
+
+        self.nearby_colliders
+            .get(&[x, y])
+            .copied()
+            .unwrap_or_default()
+    }
+
+    pub fn get_scroll_stop(&self, x: i32, y: i32) -> Option<&'static ScrollStop> {
+        let x = x.div_floor(map::SCROLL_STOP_BOX);
+        let y = y.div_floor(map::SCROLL_STOP_BOX);
+
+        self.scroll_stops.get(&[x, y])
+    }
+
+    /// Where the camera should move to this frame when it wants to go to `destination`, after
+    /// being held back or eased in by the scroll stop it is in. See [`ScrollStop::apply`].
+    pub fn apply_scroll_stop(
+        &self,
+        position: Vector2D<Number>,
+        destination: Vector2D<Number>,
+        velocity: &mut Vector2D<Number>,
+        spring: &ScrollSpring,
+    ) -> Vector2D<Number> {
+        match self.get_scroll_stop(position.x.floor(), position.y.floor()) {
+            Some(scroll_stop) => scroll_stop.apply(position, destination, velocity, spring),
+            None => {
+                *velocity = (0, 0).into();
+                destination
+            }
+        }
+    }
+
+    /// The camera zone the player is in, if any. Zones never overlap.
+    pub fn get_camera_zone(&self, position: Vector2D<Number>) -> Option<&'static CameraZone> {
+        let x = position.x.floor().div_floor(map::CAMERA_ZONE_BOX);
+        let y = position.y.floor().div_floor(map::CAMERA_ZONE_BOX);
+
+        self.camera_zones
+            .get(&[x, y])
+            .copied()
+            .unwrap_or_default()
+            .iter()
+            .copied()
+            .find(|zone| zone.contains(position))
+    }
 }
 
-#[derive(Clone, Copy)]
-pub enum MapTileSet {
-    Planets,
-    Planets2,
-    Platforms,
+/// An area of a level which holds the camera in place while the player is inside it
+pub struct CameraZone {
+    pub min: Vector2D<Number>,
+    pub max: Vector2D<Number>,
+    /// The range the centre of the camera is kept in while the player is in the zone
+    pub camera_min: Vector2D<Number>,
+    pub camera_max: Vector2D<Number>,
+}
+
+impl CameraZone {
+    pub fn contains(&self, position: Vector2D<Number>) -> bool {
+        (self.min.x..self.max.x).contains(&position.x)
+            && (self.min.y..self.max.y).contains(&position.y)
+    }
+
+    /// Moves the camera position to the nearest place the zone allows it to be
+    pub fn clamp_camera(&self, position: Vector2D<Number>) -> Vector2D<Number> {
+        (
+            position.x.clamp(self.camera_min.x, self.camera_max.x),
+            position.y.clamp(self.camera_min.y, self.camera_max.y),
+        )
+            .into()
+    }
 }
 
 #[derive(Copy, Clone)]
@@ -70,41 +223,233 @@ pub struct MapTileSetting {
     pub hflip: bool,
     pub vflip: bool,
     pub map_tile_set: MapTileSet,
+    pub animated: bool,
+}
+
+pub struct AnimationFrame {
+    pub tile_id: u16,
+    /// How many frames this animation frame is shown for
+    pub duration: u16,
+}
+
+pub struct TileAnimation {
+    pub map_tile_set: MapTileSet,
+    pub tile_id: u16,
+    pub frames: &'static [AnimationFrame],
+}
+
+impl TileAnimation {
+    /// The tile which should be shown in place of this one after `time` frames
+    pub fn tile_at(&self, time: u32) -> u16 {
+        let total_duration: u32 = self.frames.iter().map(|x| x.duration as u32).sum();
+        let mut time = time % total_duration;
+
+        for frame in self.frames {
+            if time < frame.duration as u32 {
+                return frame.tile_id;
+            }
+
+            time -= frame.duration as u32;
+        }
+
+        self.tile_id
+    }
 }
 
-pub const BLANK_TILE: MapTileSetting = MapTileSetting {
-    tile_id: (1 << 10) - 1,
-    hflip: false,
-    vflip: false,
-    map_tile_set: MapTileSet::Planets,
-};
+pub use map::{MapTileSet, BLANK_TILE, TILE_ANIMATIONS};
 
 static ALL_TRANSPARENT: &[MapTileSetting] = &[BLANK_TILE; 64];
 
-pub fn get_planet_tile_chunk(x: i32, y: i32) -> &'static [MapTileSetting] {
-    match map::PLANET_MAP_TILES.get(&[x, y]) {
-        Some(tiles) => tiles,
-        None => ALL_TRANSPARENT,
+pub struct TileLayer {
+    pub name: &'static str,
+    /// How fast this layer scrolls compared to the camera
+    pub parallax: Vector2D<Number>,
+    /// Background priority, where 0 is drawn on top
+    pub priority: u8,
+    pub visible: bool,
+    tiles: &'static phf::Map<[i32; 2], &'static [MapTileSetting]>,
+}
+
+impl TileLayer {
+    pub fn get_tile_chunk(&self, x: i32, y: i32) -> &'static [MapTileSetting] {
+        match self.tiles.get(&[x, y]) {
+            Some(tiles) => tiles,
+            None => ALL_TRANSPARENT,
+        }
     }
 }
 
-pub fn get_platform_tile_chunk(x: i32, y: i32) -> &'static [MapTileSetting] {
-    match map::PLATFORM_MAP_TILES.get(&[x, y]) {
-        Some(tiles) => tiles,
-        None => ALL_TRANSPARENT,
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PlayerStat {
+    GroundSpeed,
+    AirSpeed,
+    JumpSpeed,
+    MaxJumps,
+    /// Non-zero if the player can dash
+    CanDash,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum StatOperation {
+    Set,
+    Add,
+    Multiply,
+}
+
+impl StatOperation {
+    pub fn apply(self, value: Number, amount: Number) -> Number {
+        match self {
+            StatOperation::Set => amount,
+            StatOperation::Add => value + amount,
+            StatOperation::Multiply => value * amount,
+        }
     }
 }
 
-#[derive(Clone, Copy, PartialEq, Eq)]
-pub enum PowerUpKind {
-    JumpBoost,
-    Dash,
-    DoubleJump,
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct StatModifier {
+    pub stat: PlayerStat,
+    pub operation: StatOperation,
+    pub amount: Number,
 }
 
 pub struct PowerUp {
+    pub name: &'static str,
     pub location: Vector2D<Number>,
-    pub kind: PowerUpKind,
+    /// The name of the tag in the sprite sheet to show for this power up
+    pub sprite: &'static str,
+    pub modifiers: &'static [StatModifier],
+}
+
+pub struct MissionLog {
+    pub point: Vector2D<i32>,
+    /// How close the player needs to be to start playing the log
+    pub radius: i32,
+    pub text: &'static str,
 }
 
-pub use map::POWER_UPS;
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::*;
+
+    fn spring() -> ScrollSpring {
+        ScrollSpring::default()
+    }
+
+    /// Every scroll stop in the level whose minimum x is inside its own cell, and a camera
+    /// position in that cell. The camera stays in the cell while it's eased to the limit.
+    fn minimum_x_stops(level: &Level) -> impl Iterator<Item = (Number, Vector2D<Number>)> + '_ {
+        level.scroll_stops.entries().filter_map(|(&[x, y], stop)| {
+            let minimum_x = stop.minimum_x?;
+            if minimum_x.floor().div_floor(map::SCROLL_STOP_BOX) != x {
+                return None;
+            }
+
+            let cell_y = Number::new(y * map::SCROLL_STOP_BOX + map::SCROLL_STOP_BOX / 2);
+
+            // somewhere in the cell which is past the limit, but still close enough to it to be
+            // pulled back
+            let cell_x = (x * map::SCROLL_STOP_BOX..(x + 1) * map::SCROLL_STOP_BOX)
+                .map(Number::new)
+                .find(|&cell_x| cell_x < minimum_x && minimum_x - cell_x < 120.into())?;
+
+            Some((minimum_x, (cell_x, cell_y).into()))
+        })
+    }
+
+    #[test]
+    fn tile_animations_show_each_frame_for_its_duration_then_loop() {
+        let animation = TileAnimation {
+            map_tile_set: MapTileSet::Planets,
+            tile_id: 10,
+            frames: &[
+                AnimationFrame {
+                    tile_id: 11,
+                    duration: 3,
+                },
+                AnimationFrame {
+                    tile_id: 12,
+                    duration: 1,
+                },
+                AnimationFrame {
+                    tile_id: 13,
+                    duration: 2,
+                },
+            ],
+        };
+
+        let tiles: std::vec::Vec<_> = (0..8).map(|time| animation.tile_at(time)).collect();
+        assert_eq!(tiles, [11, 11, 11, 12, 13, 13, 11, 11]);
+
+        // long after the start, the animation is still in step
+        assert_eq!(animation.tile_at(6 * 1000 + 4), 13);
+    }
+
+    #[test]
+    fn cameras_coming_into_a_scroll_stop_ease_to_the_limit() {
+        let level = Level::by_name("main").unwrap();
+        let mut checked = 0;
+
+        for (minimum_x, start) in minimum_x_stops(level) {
+            let mut position = start;
+            let mut velocity = (0, 0).into();
+
+            for _ in 0..180 {
+                let next = level.apply_scroll_stop(position, position, &mut velocity, &spring());
+                // jumping straight to the limit would move up to half a screen in one frame, this
+                // is about as fast as the camera follows the player
+                assert!(
+                    (next - position).magnitude_squared() < (6 * 6).into(),
+                    "Camera jumped from {position:?} to {next:?}"
+                );
+
+                position = next;
+            }
+
+            assert!(
+                position.x >= minimum_x,
+                "Camera starting at {start:?} should have been eased to {minimum_x}, \
+                 but is at {position:?}"
+            );
+            checked += 1;
+        }
+
+        assert!(
+            checked > 0,
+            "The level should have some scroll stops to test"
+        );
+    }
+
+    #[test]
+    fn cameras_inside_a_scroll_stop_stay_inside() {
+        let level = Level::by_name("main").unwrap();
+
+        for (&[x, y], stop) in level.scroll_stops.entries() {
+            let Some(minimum_x) = stop.minimum_x else {
+                continue;
+            };
+
+            let position: Vector2D<Number> = (
+                minimum_x,
+                Number::new(y * map::SCROLL_STOP_BOX + map::SCROLL_STOP_BOX / 2),
+            )
+                .into();
+            if position.x.floor().div_floor(map::SCROLL_STOP_BOX) != x {
+                continue;
+            }
+
+            let mut velocity = (0, 0).into();
+            let destination = position - (6, 0).into();
+
+            assert_eq!(
+                level
+                    .apply_scroll_stop(position, destination, &mut velocity, &spring())
+                    .x,
+                minimum_x,
+                "Camera at the limit of {x}, {y} should stop there"
+            );
+        }
+    }
+}
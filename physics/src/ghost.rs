@@ -0,0 +1,363 @@
+use agb_fixnum::{num, Num, Vector2D};
+use alloc::vec::Vec;
+use util::{seal, unseal, Reader, HEADER_SIZE};
+
+use crate::{Player, PlayerFacing, Pose, ReplayError};
+
+const MAGIC: [u8; 4] = *b"BTSg";
+/// Bumped whenever the layout of the frames changes
+const VERSION: u16 = 2;
+
+// Each frame starts with flags saying which parts of it have changed since the frame before
+/// The position moved by less than 8 pixels each way, packed into a byte
+const NEAR: u8 = 1 << 0;
+/// The position moved further, so the whole position follows
+const FAR: u8 = 1 << 1;
+const ANGLE: u8 = 1 << 2;
+const POSE: u8 = 1 << 3;
+
+/// How the player looked on a frame of a run
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct GhostFrame {
+    /// Where the player is drawn, in whole pixels
+    pub position: Vector2D<i32>,
+    /// 256ths of a turn, as used by `AffineMatrix::from_rotation`
+    pub angle: u8,
+    /// Animation steps wrap around after 256
+    pub pose: Pose,
+    pub facing: PlayerFacing,
+}
+
+impl GhostFrame {
+    pub fn of(player: &Player) -> Self {
+        let pose = match player.pose() {
+            Pose::Walking(step) => Pose::Walking(step % 256),
+            Pose::Jumping(step) => Pose::Jumping(step % 256),
+            pose => pose,
+        };
+
+        // the player is rotated so that up points away from gravity
+        let up = player.get_normal();
+
+        Self {
+            position: player.rendered_position().floor(),
+            angle: turns(-up.x, -up.y),
+            pose,
+            facing: player.facing,
+        }
+    }
+
+    fn pose_bytes(&self) -> [u8; 2] {
+        let (kind, step) = match self.pose {
+            Pose::Idle => (0, 0),
+            Pose::Walking(step) => (1, step),
+            Pose::Jumping(step) => (2, step),
+            Pose::Falling => (3, 0),
+        };
+        let facing = match self.facing {
+            PlayerFacing::Right => 0,
+            PlayerFacing::Left => 1 << 2,
+        };
+
+        [kind | facing, step as u8]
+    }
+}
+
+/// The angle from the x axis towards the y axis, in 256ths of a turn. This is only an
+/// approximation, but is within a step of the real angle.
+fn turns(y: Num<i32, 8>, x: Num<i32, 8>) -> u8 {
+    let (abs_x, abs_y) = (x.abs(), y.abs());
+    if abs_x == 0.into() && abs_y == 0.into() {
+        return 0;
+    }
+
+    // atan(z) for z in [0, 1] is close to z / 8 + 0.0435 z (1 - z) turns
+    let z: Num<i32, 16> =
+        Num::from_raw((abs_x.min(abs_y).to_raw() << 16) / abs_x.max(abs_y).to_raw());
+    let octant = z / 8 + z * (num!(1.) - z) * num!(0.0435);
+
+    let quadrant = if abs_y > abs_x {
+        num!(0.25) - octant
+    } else {
+        octant
+    };
+    let angle = match (x < 0.into(), y < 0.into()) {
+        (false, false) => quadrant,
+        (true, false) => num!(0.5) - quadrant,
+        (true, true) => num!(0.5) + quadrant,
+        (false, true) => num!(1.) - quadrant,
+    };
+
+    ((angle * 256 + num!(0.5)).floor() & 0xff) as u8
+}
+
+/// A run, compactly stored a frame at a time so that it can be shown again as a ghost
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Ghost {
+    /// Index of the level in the list of levels
+    level: u8,
+    frames: u32,
+    data: Vec<u8>,
+}
+
+impl Ghost {
+    pub fn level(&self) -> u8 {
+        self.level
+    }
+
+    /// How long the run took
+    pub fn frames(&self) -> u32 {
+        self.frames
+    }
+
+    pub fn playback(self) -> GhostPlayback {
+        GhostPlayback {
+            ghost: self,
+            offset: 0,
+            last: None,
+        }
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(HEADER_SIZE + 5 + self.data.len());
+        bytes.resize(HEADER_SIZE, 0);
+        bytes.push(self.level);
+        bytes.extend_from_slice(&self.frames.to_le_bytes());
+        bytes.extend_from_slice(&self.data);
+
+        seal(MAGIC, VERSION, &mut bytes);
+        bytes
+    }
+
+    /// Reads a ghost from the start of the bytes. Anything after the end of it is ignored.
+    pub fn decode(bytes: &[u8]) -> Result<Self, ReplayError> {
+        let mut payload = Reader::new(unseal(MAGIC, VERSION, bytes)?);
+
+        Ok(Self {
+            level: payload.u8().ok_or(ReplayError::Corrupt)?,
+            frames: payload.u32().ok_or(ReplayError::Corrupt)?,
+            data: payload.rest().into(),
+        })
+    }
+}
+
+/// Builds up a ghost as the run is played
+pub struct GhostRecorder {
+    ghost: Ghost,
+    last: Option<GhostFrame>,
+}
+
+impl GhostRecorder {
+    pub fn new(level: u8) -> Self {
+        Self {
+            ghost: Ghost {
+                level,
+                frames: 0,
+                data: Vec::new(),
+            },
+            last: None,
+        }
+    }
+
+    pub fn record(&mut self, frame: GhostFrame) {
+        let data = &mut self.ghost.data;
+        let flags_at = data.len();
+        data.push(0);
+        let mut flags = 0;
+
+        let delta = self.last.map(|last| frame.position - last.position);
+        match delta {
+            Some(delta) if delta == (0, 0).into() => {}
+            Some(delta) if (-8..8).contains(&delta.x) && (-8..8).contains(&delta.y) => {
+                flags |= NEAR;
+                data.push(((delta.x as u8) << 4) | (delta.y as u8 & 0xf));
+            }
+            _ => {
+                flags |= FAR;
+                data.extend_from_slice(&(frame.position.x as i16).to_le_bytes());
+                data.extend_from_slice(&(frame.position.y as i16).to_le_bytes());
+            }
+        }
+
+        if self.last.map_or(true, |last| last.angle != frame.angle) {
+            flags |= ANGLE;
+            data.push(frame.angle);
+        }
+
+        let pose = frame.pose_bytes();
+        if self.last.map_or(true, |last| last.pose_bytes() != pose) {
+            flags |= POSE;
+            data.extend_from_slice(&pose);
+        }
+
+        data[flags_at] = flags;
+        self.last = Some(frame);
+        self.ghost.frames += 1;
+    }
+
+    pub fn frames(&self) -> u32 {
+        self.ghost.frames
+    }
+
+    pub fn finish(self) -> Ghost {
+        self.ghost
+    }
+}
+
+/// Goes through a ghost's frames in order
+pub struct GhostPlayback {
+    ghost: Ghost,
+    /// Where the next frame starts in the ghost's data
+    offset: usize,
+    last: Option<GhostFrame>,
+}
+
+impl GhostPlayback {
+    pub fn ghost(&self) -> &Ghost {
+        &self.ghost
+    }
+}
+
+impl Iterator for GhostPlayback {
+    type Item = GhostFrame;
+
+    fn next(&mut self) -> Option<GhostFrame> {
+        let mut data = Reader::new(self.ghost.data.get(self.offset..)?);
+        let flags = data.u8()?;
+        let mut frame = self.last.unwrap_or(GhostFrame {
+            position: (0, 0).into(),
+            angle: 0,
+            pose: Pose::Idle,
+            facing: PlayerFacing::Right,
+        });
+
+        if flags & NEAR != 0 {
+            let delta = data.u8()?;
+            // shifting the signed byte keeps the sign of each half
+            frame.position += ((delta as i8 >> 4) as i32, ((delta << 4) as i8 >> 4) as i32).into();
+        }
+        if flags & FAR != 0 {
+            frame.position = (data.u16()? as i16 as i32, data.u16()? as i16 as i32).into();
+        }
+        if flags & ANGLE != 0 {
+            frame.angle = data.u8()?;
+        }
+        if flags & POSE != 0 {
+            let kind = data.u8()?;
+            let step = data.u8()? as usize;
+
+            frame.pose = match kind & 0b11 {
+                0 => Pose::Idle,
+                1 => Pose::Walking(step),
+                2 => Pose::Jumping(step),
+                _ => Pose::Falling,
+            };
+            frame.facing = if kind & (1 << 2) != 0 {
+                PlayerFacing::Left
+            } else {
+                PlayerFacing::Right
+            };
+        }
+
+        self.offset = self.ghost.data.len() - data.rest().len();
+        self.last = Some(frame);
+        Some(frame)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+    use map::Level;
+
+    use crate::{PlayerInput, Simulation};
+
+    use super::*;
+
+    #[test]
+    fn angles_match_the_rotation_they_are_drawn_with() {
+        for angle in 0..256 {
+            let turn = Num::<i32, 8>::from_raw(angle);
+            let found = turns(turn.sin(), turn.cos()) as i32;
+
+            let difference = (found - angle).rem_euclid(256);
+            assert!(
+                difference <= 1 || difference == 255,
+                "{angle} came back as {found}"
+            );
+        }
+
+        assert_eq!(turns((-1).into(), 0.into()), 192);
+    }
+
+    #[test]
+    fn ghosts_play_back_the_frames_they_recorded() {
+        let mut simulation = Simulation::new(Level::by_name("main").unwrap());
+        let mut recorder = GhostRecorder::new(0);
+        let mut recorded = vec![];
+
+        for frame in 0..400 {
+            let input = PlayerInput {
+                direction: [1, 0, -1][frame / 100 % 3],
+                jump_pressed: frame % 80 < 20,
+                jump_just_pressed: frame % 80 == 0,
+                dash_just_pressed: false,
+            };
+            simulation.frame(&input);
+
+            let ghost_frame = GhostFrame::of(simulation.player());
+            recorder.record(ghost_frame);
+            recorded.push(ghost_frame);
+        }
+
+        // a teleport, such as recovering, doesn't fit in a small move
+        let mut teleported = *recorded.last().unwrap();
+        teleported.position += (300, -200).into();
+        recorder.record(teleported);
+        recorded.push(teleported);
+
+        let ghost = recorder.finish();
+        assert_eq!(ghost.frames(), 401);
+        assert!(
+            ghost.data.len() < 3 * 401,
+            "Ghost took {} bytes",
+            ghost.data.len()
+        );
+
+        let ghost = Ghost::decode(&ghost.encode()).unwrap();
+        assert_eq!(ghost.playback().collect::<Vec<_>>(), recorded);
+    }
+
+    #[test]
+    fn time_attack_runs_end_at_the_finish() {
+        let level = Level::by_name("main").unwrap();
+        let finish = level
+            .finish
+            .as_ref()
+            .expect("The main level should have a finish");
+
+        // a little way before the finish, on the ceiling of the room the level ends in, which is
+        // upside down so walking left goes right
+        let mut simulation = Simulation::new(level);
+        simulation.player_mut().position = (finish.point + (-80, -10).into()).change_base();
+        let mut recorder = GhostRecorder::new(0);
+
+        let input = PlayerInput {
+            direction: -1,
+            ..Default::default()
+        };
+        while !simulation.has_finished() {
+            assert!(recorder.frames() < 300, "Should have reached the finish");
+
+            simulation.frame(&input);
+            recorder.record(GhostFrame::of(simulation.player()));
+        }
+
+        let ghost = Ghost::decode(&recorder.finish().encode()).unwrap();
+        assert!(ghost.frames() > 0);
+
+        // the ghost ends where the player finished
+        let last = ghost.playback().last();
+        assert_eq!(last, Some(GhostFrame::of(simulation.player())));
+    }
+}
@@ -0,0 +1,697 @@
+#![no_std]
+
+extern crate alloc;
+
+use agb_fixnum::{num, Vector2D};
+use alloc::vec::Vec;
+use map::{Body, Level};
+use util::{
+    resolve_collisions, Buttons, Circle, Collider, InputSnapshot, ItemSet, Number, SaveGame,
+};
+
+mod ghost;
+mod player;
+mod power_ups;
+mod replay;
+mod terrain;
+
+pub use ghost::{Ghost, GhostFrame, GhostPlayback, GhostRecorder};
+pub use player::{JumpState, Player, PlayerFacing, Pose};
+pub use power_ups::{PowerUpPickup, PowerUpState};
+pub use replay::{position_hash, Desync, Playback, Recording, ReplayError, HASH_INTERVAL};
+pub use terrain::{DynamicCollider, Terrain};
+
+use player::{DashState, GroundState};
+use power_ups::PickupProgress;
+use terrain::DynamicAndStaticColliders;
+
+/// What the player is doing with the controls this frame
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub struct PlayerInput {
+    /// -1 for left, 1 for right and 0 for neither
+    pub direction: i32,
+    pub jump_pressed: bool,
+    pub jump_just_pressed: bool,
+    pub dash_just_pressed: bool,
+}
+
+impl From<&InputSnapshot> for PlayerInput {
+    fn from(input: &InputSnapshot) -> Self {
+        Self {
+            direction: input.x_tri(),
+            jump_pressed: input.is_pressed(Buttons::A),
+            jump_just_pressed: input.is_just_pressed(Buttons::A),
+            dash_just_pressed: input.is_just_pressed(Buttons::B),
+        }
+    }
+}
+
+/// Something which happened during a frame, which the game may want to play a sound for
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Event {
+    Jumped,
+    Dashed,
+    /// The player hit the ground after falling
+    Landed,
+    /// The player touched something which kills them, and has started recovering
+    Died,
+    /// The player has finished recovering and is back in control
+    Recovered,
+    /// The player touched a power up, which they get once it has finished animating
+    PowerUpTouched,
+    PowerUpCollected,
+}
+
+/// The events which happened during a frame
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub struct Events(u8);
+
+impl Events {
+    const ALL: [Event; 7] = [
+        Event::Jumped,
+        Event::Dashed,
+        Event::Landed,
+        Event::Died,
+        Event::Recovered,
+        Event::PowerUpTouched,
+        Event::PowerUpCollected,
+    ];
+
+    fn insert(&mut self, event: Event) {
+        self.0 |= 1 << event as u8;
+    }
+
+    pub fn contains(&self, event: Event) -> bool {
+        self.0 & (1 << event as u8) != 0
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Event> + '_ {
+        Self::ALL.into_iter().filter(|&event| self.contains(event))
+    }
+}
+
+pub struct RecoveringState {
+    pub recover_to: Vector2D<Number>,
+    starting_from: Vector2D<Number>,
+    starting_reverse_local_gravity: Vector2D<Number>,
+    destination_reverse_local_gravity: Vector2D<Number>,
+    pub time: u32,
+}
+
+pub enum PlayerState {
+    Playing,
+    Recovering(RecoveringState),
+}
+
+/// A collider the player is being pulled towards. If it is part of a moving body, then
+/// the body and index of the collider within it are kept so it can follow the body.
+#[derive(Clone)]
+struct GravitySource {
+    collider: Collider,
+    body: Option<(&'static Body, usize)>,
+}
+
+impl GravitySource {
+    fn refresh(&mut self, terrain: &Terrain) {
+        if let Some((body, idx)) = self.body {
+            if let Some(dynamic_collider) = terrain.dynamic_collider(body) {
+                self.collider = dynamic_collider.colliders[idx].clone();
+            }
+        }
+    }
+}
+
+/// The most floor-like surface the player touched this frame
+struct GroundContact {
+    cosine_of_floor_angle: Number,
+    body: Option<&'static Body>,
+}
+
+/// The player and the level they're moving around in
+pub struct Simulation {
+    pub terrain: Terrain,
+    pub physics: PlayerPhysics,
+    /// The power ups which are still in the level
+    pub power_ups: Vec<PowerUpPickup>,
+    collected_power_ups: ItemSet,
+}
+
+impl Simulation {
+    pub fn new(level: &'static Level) -> Self {
+        Self {
+            terrain: Terrain::new(level),
+            physics: PlayerPhysics::new(level),
+            power_ups: level
+                .power_ups
+                .iter()
+                .enumerate()
+                .map(|(idx, powerup)| PowerUpPickup::new(idx, powerup))
+                .collect(),
+            collected_power_ups: ItemSet::default(),
+        }
+    }
+
+    /// Puts the player back where the save left them, with the power ups they had collected
+    pub fn restore(&mut self, save: &SaveGame) {
+        let player = self.player_mut();
+        player.set_stats(&save.stats);
+        if let Some(point) = save.recovery_point {
+            player.position = point;
+        }
+
+        self.collected_power_ups = save.collected_power_ups;
+        self.power_ups
+            .retain(|powerup| !save.collected_power_ups.contains(powerup.index()));
+    }
+
+    /// Which of the level's power ups the player has, by their index in the level
+    pub fn collected_power_ups(&self) -> ItemSet {
+        self.collected_power_ups
+    }
+
+    pub fn player(&self) -> &Player {
+        &self.physics.player
+    }
+
+    /// Whether the player has reached the end of a time attack run through the level
+    pub fn has_finished(&self) -> bool {
+        self.physics
+            .level
+            .finish
+            .as_ref()
+            .is_some_and(|finish| finish.is_reached(self.player().position.floor()))
+    }
+
+    pub fn player_mut(&mut self) -> &mut Player {
+        &mut self.physics.player
+    }
+
+    /// Moves everything on by a frame
+    pub fn frame(&mut self, input: &PlayerInput) -> Events {
+        self.terrain.update(self.physics.player.position);
+        let mut events = self.physics.frame(input, &self.terrain);
+
+        let player = &mut self.physics.player;
+        self.power_ups.retain_mut(
+            |powerup_pickup| match powerup_pickup.update(player.position) {
+                PickupProgress::Nothing => true,
+                PickupProgress::Touched => {
+                    events.insert(Event::PowerUpTouched);
+                    true
+                }
+                PickupProgress::Collected(powerup) => {
+                    player.apply_powerup(powerup);
+                    self.collected_power_ups.insert(powerup_pickup.index());
+                    events.insert(Event::PowerUpCollected);
+                    false
+                }
+            },
+        );
+
+        events
+    }
+}
+
+/// How the player moves, which is kept apart from the terrain so that the player can be moved
+/// while looking at the terrain
+pub struct PlayerPhysics {
+    pub level: &'static Level,
+    pub player: Player,
+    last_gravity_source: Option<GravitySource>,
+    pub player_state: PlayerState,
+}
+
+impl PlayerPhysics {
+    fn new(level: &'static Level) -> Self {
+        Self {
+            level,
+            player: Player::new(level.start_point),
+            last_gravity_source: None,
+            player_state: PlayerState::Playing,
+        }
+    }
+
+    /// returns whether or not the jump actually happened
+    fn handle_jump_input(&mut self, terrain: &Terrain) -> bool {
+        let supporting_body = self.player.supporting_body;
+        if !self.player.handle_jump_input() {
+            return false;
+        }
+
+        self.leave_supporting_body(supporting_body, terrain);
+        true
+    }
+
+    /// The player keeps the momentum of the body they were standing on when they leave it
+    fn leave_supporting_body(&mut self, body: Option<&'static Body>, terrain: &Terrain) {
+        if let Some(dynamic_collider) = body.and_then(|body| terrain.dynamic_collider(body)) {
+            self.player.speed += dynamic_collider.velocity;
+        }
+
+        self.player.supporting_body = None;
+    }
+
+    /// Moves the player along with the body they are standing on
+    fn ride_supporting_body(&mut self, terrain: &Terrain) {
+        if let Some(body) = self.player.supporting_body {
+            match terrain.dynamic_collider(body) {
+                Some(dynamic_collider) => self.player.position += dynamic_collider.velocity,
+                None => self.player.supporting_body = None,
+            }
+        }
+    }
+
+    fn handle_player_death(&mut self, events: &mut Events, terrain: &Terrain) {
+        events.insert(Event::Died);
+
+        self.player.supporting_body = None;
+
+        let point_to_recover_to = self.level.get_recovery_point(self.player.position);
+        self.player_state = PlayerState::Recovering(RecoveringState {
+            recover_to: point_to_recover_to,
+            starting_from: self.player.position,
+            starting_reverse_local_gravity: (self.player.position
+                - self
+                    .last_gravity_source
+                    .as_ref()
+                    .unwrap()
+                    .collider
+                    .closest_point(self.player.position))
+            .fast_normalise(),
+            destination_reverse_local_gravity: (point_to_recover_to
+                - get_gravity_source(terrain.colliders(point_to_recover_to), point_to_recover_to)
+                    .1)
+                .fast_normalise(),
+            time: 0,
+        });
+    }
+
+    /// returns the most floor-like surface collided with if there is one. So None = not touching the ground
+    fn handle_collider_collisions(
+        &mut self,
+        events: &mut Events,
+        colliders: DynamicAndStaticColliders,
+        terrain: &Terrain,
+    ) -> Option<GroundContact> {
+        let player_circle = Circle {
+            position: self.player.position,
+            radius: 8.into(),
+        };
+
+        // contacts refer to colliders by index, so gather them once rather than walking the
+        // iterator for each contact
+        let colliders: Vec<_> = colliders.iter_with_body().collect();
+
+        if colliders
+            .iter()
+            .any(|(x, _)| x.tag.is_kills_player() && x.collides_circle(&player_circle))
+        {
+            self.handle_player_death(events, terrain);
+        }
+
+        let resolution = resolve_collisions(colliders.iter().map(|&(x, _)| x), player_circle);
+        self.player.position = resolution.position;
+
+        if resolution.manifold.is_crushed() && matches!(self.player_state, PlayerState::Playing) {
+            self.handle_player_death(events, terrain);
+            return None;
+        }
+
+        let mut ground_contact: Option<GroundContact> = None;
+
+        for contact in resolution.manifold.iter() {
+            let (collider, dynamic_collider) = colliders[contact.collider_index];
+            let normal = contact.normal;
+
+            let dot = normal.dot(self.player.speed);
+            if dot < 0.into() {
+                self.player.speed -= normal * dot;
+            }
+
+            let cosine_of_floor_angle = self.player.get_normal().dot(normal);
+            // 0.7 is approximately sqrt(2) / 2 which is about 45 degrees
+            if ground_contact
+                .as_ref()
+                .map_or(true, |x| cosine_of_floor_angle > x.cosine_of_floor_angle)
+            {
+                self.player.surface_normal = normal;
+                ground_contact = Some(GroundContact {
+                    cosine_of_floor_angle,
+                    body: dynamic_collider.map(|(x, _)| x.body),
+                });
+            }
+
+            // the player has already been moved with the body they're standing on
+            let is_supporting_body = match (dynamic_collider, self.player.supporting_body) {
+                (Some((dynamic_collider, _)), Some(body)) => {
+                    core::ptr::eq(dynamic_collider.body, body)
+                }
+                _ => false,
+            };
+            if !is_supporting_body {
+                self.player.position += collider.velocity;
+            }
+        }
+
+        ground_contact
+    }
+
+    fn get_gravity_source(
+        &mut self,
+        colliders: DynamicAndStaticColliders,
+        terrain: &Terrain,
+    ) -> Vector2D<Number> {
+        if colliders.is_empty() {
+            let source = self
+                .last_gravity_source
+                .as_mut()
+                .expect("We should have a gravity source if we're in empty space");
+            source.refresh(terrain);
+            source.collider.closest_point(self.player.position)
+        } else {
+            let (gravity_source, gravity_source_position) =
+                get_gravity_source(colliders, self.player.position);
+
+            self.last_gravity_source = Some(gravity_source);
+            gravity_source_position
+        }
+    }
+
+    fn physics_frame(&mut self, input: &PlayerInput, events: &mut Events, terrain: &Terrain) {
+        self.ride_supporting_body(terrain);
+
+        let colliders = terrain.colliders(self.player.position);
+        let gravity_source = self.get_gravity_source(colliders, terrain);
+
+        let gravity_direction = (gravity_source - self.player.position).fast_normalise();
+
+        let gravity = if self.player.jump_state == JumpState::Jumping && input.jump_pressed {
+            gravity_direction / 128
+        } else {
+            gravity_direction / 10
+        };
+
+        let old_speed = self.player.speed;
+        let was_on_ground = self.player.is_on_ground();
+
+        self.player.speed += gravity;
+        self.player.position += self.player.speed;
+
+        let ground_contact = self.handle_collider_collisions(events, colliders, terrain);
+        let contact_body = ground_contact.as_ref().and_then(|x| x.body);
+
+        self.player.ground_state = match ground_contact.map(|x| x.cosine_of_floor_angle) {
+            Some(value) => {
+                if value > num!(0.8) {
+                    // approximately < 45 degree angle. So definitely on the ground
+                    self.player.jump_state = JumpState::HasJump;
+                    self.player.dash_state = DashState::Available;
+                    self.player.jumps_remaining = self.player.max_jumps;
+
+                    // Apply a reasonably high amount of friction
+                    self.player.speed *= num!(0.8);
+
+                    GroundState::OnGround
+                } else if value > num!(0.7) {
+                    // just over 45 degrees (since 0.7 ~= sqrt(2) / 2). Still should be considered ground, but apply less friction
+                    self.player.jump_state = JumpState::HasJump; // should allow for another jump
+                    self.player.dash_state = DashState::Available;
+                    self.player.jumps_remaining = self.player.max_jumps;
+                    self.player.speed *= num!(0.90);
+
+                    GroundState::OnGround
+                } else {
+                    // hit something which isn't floor-like
+                    self.player.speed *= num!(0.95);
+                    GroundState::InAir
+                }
+            }
+            None => {
+                // hit something which isn't floor-like
+                self.player.speed *= num!(0.95);
+                GroundState::InAir
+            }
+        };
+
+        let is_on_ground = self.player.is_on_ground();
+        let new_supporting_body = if is_on_ground { contact_body } else { None };
+
+        let old_supporting_body = self.player.supporting_body;
+        let is_same_body = match (old_supporting_body, new_supporting_body) {
+            (Some(old), Some(new)) => core::ptr::eq(old, new),
+            (None, None) => true,
+            _ => false,
+        };
+        if !is_same_body {
+            self.leave_supporting_body(old_supporting_body, terrain);
+            self.player.supporting_body = new_supporting_body;
+        }
+
+        if !was_on_ground && is_on_ground && old_speed.dot(gravity_direction) > 1.into() {
+            events.insert(Event::Landed);
+        }
+
+        if self.player.speed.magnitude_squared() < num!(0.005) {
+            self.player.speed = (0, 0).into();
+        }
+
+        self.player.update_facing(-gravity_direction);
+    }
+
+    fn frame(&mut self, input: &PlayerInput, terrain: &Terrain) -> Events {
+        let mut events = Events::default();
+
+        match &mut self.player_state {
+            PlayerState::Playing => {
+                if self
+                    .player
+                    .handle_direction_input(input.direction, input.dash_just_pressed)
+                {
+                    events.insert(Event::Dashed);
+                }
+                self.physics_frame(input, &mut events, terrain);
+
+                if input.jump_just_pressed && self.handle_jump_input(terrain) {
+                    events.insert(Event::Jumped);
+                }
+
+                self.player.frame();
+            }
+            PlayerState::Recovering(recover) => {
+                recover.time += 1;
+                match recover.time {
+                    0..16 => {
+                        self.player.speed = (0, 0).into();
+                    }
+                    16..64 => {
+                        let time = recover.time as i32 - 16;
+                        let time = Number::new(time) / (64 - 16);
+
+                        let start_position_line = recover.starting_from
+                            + recover.starting_reverse_local_gravity * time * 30;
+                        let ending_position_line = recover.recover_to
+                            + recover.destination_reverse_local_gravity * (-time + 1) * 30;
+
+                        let position =
+                            start_position_line * (-time + 1) + ending_position_line * time;
+
+                        self.player.position = position;
+                    }
+                    64..80 => {}
+                    80.. => {
+                        self.player_state = PlayerState::Playing;
+                        events.insert(Event::Recovered);
+                    }
+                }
+            }
+        }
+
+        events
+    }
+}
+
+fn get_gravity_source(
+    colliders: DynamicAndStaticColliders<'_>,
+    position: Vector2D<Number>,
+) -> (GravitySource, Vector2D<Number>) {
+    colliders
+        .iter_with_body()
+        .filter(|(x, _)| x.tag.is_gravitational())
+        .map(|(collider, body)| (collider, body, collider.closest_point(position)))
+        .min_by_key(|&(_, _, closest_point)| (closest_point - position).magnitude_squared())
+        .map(|(collider, body, closest_point)| {
+            (
+                GravitySource {
+                    collider: collider.clone(),
+                    body: body.map(|(dynamic_collider, idx)| (dynamic_collider.body, idx)),
+                },
+                closest_point,
+            )
+        })
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use util::{InputSource, ScriptedInput};
+
+    use super::*;
+
+    fn level() -> &'static Level {
+        Level::by_name("main").unwrap()
+    }
+
+    /// Runs frames with the same input until the event happens, giving how many frames it took
+    fn run_until(
+        simulation: &mut Simulation,
+        input: &PlayerInput,
+        event: Event,
+        max_frames: usize,
+    ) -> Option<usize> {
+        (1..=max_frames).find(|_| simulation.frame(input).contains(event))
+    }
+
+    /// Lets the player fall onto the ground and stop moving
+    fn landed_simulation() -> Simulation {
+        let mut simulation = Simulation::new(level());
+        for _ in 0..120 {
+            simulation.frame(&PlayerInput::default());
+        }
+
+        assert!(simulation.player().is_on_ground());
+        simulation
+    }
+
+    #[test]
+    fn player_falls_onto_the_ground() {
+        let mut simulation = Simulation::new(level());
+        let start = simulation.player().position;
+
+        let frames = run_until(&mut simulation, &PlayerInput::default(), Event::Landed, 300);
+
+        assert!(frames.is_some(), "Player should land");
+        assert!(simulation.player().is_on_ground());
+        assert_ne!(simulation.player().position, start);
+    }
+
+    #[test]
+    fn jumping_leaves_the_ground_and_lands_again() {
+        let mut simulation = landed_simulation();
+        let ground = simulation.player().position;
+        let up = simulation.player().get_normal();
+
+        let jump = PlayerInput {
+            jump_pressed: true,
+            jump_just_pressed: true,
+            ..Default::default()
+        };
+        assert!(simulation.frame(&jump).contains(Event::Jumped));
+
+        let hold = PlayerInput {
+            jump_pressed: true,
+            ..Default::default()
+        };
+        for _ in 0..20 {
+            simulation.frame(&hold);
+        }
+
+        let height = (simulation.player().position - ground).dot(up);
+        assert!(height > 16.into(), "Player only jumped {height}");
+        assert!(!simulation.player().is_on_ground());
+
+        let landed = run_until(&mut simulation, &PlayerInput::default(), Event::Landed, 300);
+        assert!(landed.is_some(), "Player should come back down");
+    }
+
+    #[test]
+    fn player_can_only_jump_when_they_have_a_jump() {
+        let mut simulation = landed_simulation();
+        let jump = PlayerInput {
+            jump_just_pressed: true,
+            ..Default::default()
+        };
+
+        assert!(simulation.frame(&jump).contains(Event::Jumped));
+        assert!(!simulation.frame(&jump).contains(Event::Jumped));
+    }
+
+    #[test]
+    fn touching_a_killing_collider_recovers_the_player() {
+        let level = level();
+        let (collider, near) = (-4096..4096)
+            .step_by(32)
+            .flat_map(|y| (-4096..4096).step_by(32).map(move |x| (x, y)))
+            .find_map(|(x, y)| {
+                level
+                    .get_nearby(x, y)
+                    .iter()
+                    .find(|collider| collider.tag.is_kills_player())
+                    .map(|collider| (collider, Vector2D::new(x, y).change_base()))
+            })
+            .expect("Level should have something which kills the player");
+
+        let mut simulation = landed_simulation();
+        simulation.player_mut().position = collider.closest_point(near);
+
+        let events = simulation.frame(&PlayerInput::default());
+        assert!(events.contains(Event::Died));
+
+        let PlayerState::Recovering(recovering) = &simulation.physics.player_state else {
+            panic!("Player should be recovering");
+        };
+        let recover_to = recovering.recover_to;
+
+        let recovered = run_until(
+            &mut simulation,
+            &PlayerInput::default(),
+            Event::Recovered,
+            100,
+        );
+        assert!(recovered.is_some(), "Player should finish recovering");
+        assert!(
+            (simulation.player().position - recover_to).magnitude_squared() < (8 * 8).into(),
+            "Player should be back at the recovery point"
+        );
+    }
+
+    #[test]
+    fn the_same_input_gives_the_same_result() {
+        let inputs = (0..300).map(|frame| PlayerInput {
+            direction: [-1, 0, 1][frame / 50 % 3],
+            jump_pressed: frame % 40 < 10,
+            jump_just_pressed: frame % 40 == 0,
+            dash_just_pressed: false,
+        });
+
+        let mut first = Simulation::new(level());
+        let mut second = Simulation::new(level());
+        for input in inputs {
+            assert_eq!(first.frame(&input), second.frame(&input));
+        }
+
+        assert_eq!(first.player().position, second.player().position);
+        assert_eq!(first.player().speed, second.player().speed);
+    }
+
+    #[test]
+    fn scripted_input_drives_the_player() {
+        let mut simulation = landed_simulation();
+        let start = simulation.player().position;
+
+        let steps = [(Buttons::RIGHT, 30), (Buttons::RIGHT | Buttons::A, 1)];
+        let mut script = ScriptedInput::new(&steps);
+        let mut input = InputSnapshot::default();
+        let mut jumped = false;
+
+        while !script.is_finished() {
+            input = script.next_snapshot(input);
+            jumped |= simulation
+                .frame(&PlayerInput::from(&input))
+                .contains(Event::Jumped);
+        }
+
+        assert!(jumped, "Player should jump when A is pressed");
+        assert_eq!(simulation.player().facing, PlayerFacing::Right);
+        assert_ne!(simulation.player().position, start);
+    }
+}
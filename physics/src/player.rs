@@ -0,0 +1,250 @@
+use agb_fixnum::{num, Vector2D};
+use map::{Body, PlayerStat, PowerUp};
+use util::{Number, SavedStats};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlayerFacing {
+    Left,
+    Right,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JumpState {
+    HasJump,
+    Jumping,
+    Falling,
+}
+
+/// Which animation the player is shown with, along with how far through it they are
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pose {
+    Idle,
+    Walking(usize),
+    Jumping(usize),
+    Falling,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum GroundState {
+    OnGround,
+    InAir,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum DashState {
+    Available,
+    Used,
+}
+
+pub struct Player {
+    // the direction away from local gravity, which the player stands up towards
+    up: Vector2D<Number>,
+    // the normal of the surface the player is on, not the gravity source
+    pub(crate) surface_normal: Vector2D<Number>,
+    pub speed: Vector2D<Number>,
+    pub position: Vector2D<Number>,
+    pub facing: PlayerFacing,
+    pub(crate) ground_state: GroundState,
+    ground_speed: Number,
+    air_speed: Number,
+
+    can_dash: bool,
+    pub(crate) dash_state: DashState,
+    pub(crate) max_jumps: usize,
+    pub(crate) jumps_remaining: usize,
+    pub jump_state: JumpState,
+    jump_speed: Number,
+
+    // the moving body the player is standing on, if any
+    pub(crate) supporting_body: Option<&'static Body>,
+
+    /// Frames since the player last jumped, used for animation
+    pub frame: usize,
+}
+
+impl Player {
+    pub fn new(position: Vector2D<Number>) -> Self {
+        Self {
+            up: (0, -1).into(),
+            speed: (0, 0).into(),
+            position,
+            jump_state: JumpState::Falling,
+            facing: PlayerFacing::Right,
+            ground_state: GroundState::InAir,
+            surface_normal: (0, 0).into(),
+
+            jump_speed: num!(2.2),
+            ground_speed: num!(0.25),
+            air_speed: num!(0.0625),
+
+            can_dash: false,
+            dash_state: DashState::Available,
+            jumps_remaining: 1,
+            max_jumps: 1,
+
+            supporting_body: None,
+
+            frame: 0,
+        }
+    }
+
+    pub(crate) fn update_facing(&mut self, direction: Vector2D<Number>) {
+        self.up = direction;
+    }
+
+    /// The direction away from local gravity
+    pub fn get_normal(&self) -> Vector2D<Number> {
+        self.up
+    }
+
+    /// Returns whether the player dashed
+    pub(crate) fn handle_direction_input(&mut self, x: i32, is_dashing: bool) -> bool {
+        if x == 0 {
+            return false;
+        }
+
+        let dashed = self.can_dash && is_dashing && self.dash_state == DashState::Available;
+        let dash = if dashed {
+            self.dash_state = DashState::Used;
+            num!(3.)
+        } else {
+            num!(0.)
+        };
+
+        let (acceleration, normal) = if self.is_on_ground() {
+            if self.surface_normal.dot(self.get_normal()) > num!(0.7) {
+                (
+                    Vector2D::new(0.into(), Number::new(x)) * (self.ground_speed + dash),
+                    self.surface_normal,
+                )
+            } else {
+                (
+                    Vector2D::new(0.into(), Number::new(x)) * (self.ground_speed + dash),
+                    self.get_normal(),
+                )
+            }
+        } else {
+            (
+                Vector2D::new(0.into(), Number::new(x)) * (self.air_speed + dash),
+                self.get_normal(),
+            )
+        };
+
+        let rotated_acceleration = (
+            normal.x * acceleration.x - normal.y * acceleration.y,
+            normal.y * acceleration.x + normal.x * acceleration.y,
+        )
+            .into();
+
+        self.speed += rotated_acceleration;
+
+        if x < 0 {
+            self.facing = PlayerFacing::Left;
+        } else {
+            self.facing = PlayerFacing::Right;
+        }
+
+        dashed
+    }
+
+    /// returns whether or not the jump actually happened
+    pub(crate) fn handle_jump_input(&mut self) -> bool {
+        if self.jump_state == JumpState::HasJump {
+            let normal = self.get_normal();
+
+            let dot = self.speed.dot(normal);
+            if dot < 0.into() {
+                self.speed -= normal * dot;
+            }
+            self.speed += normal * self.jump_speed;
+
+            self.position += self.speed;
+
+            self.jump_state = JumpState::Jumping;
+            self.jumps_remaining -= 1;
+            self.frame = 0;
+
+            return true;
+        }
+
+        false
+    }
+
+    pub fn rendered_position(&self) -> Vector2D<Number> {
+        self.position - (8, 8).into()
+    }
+
+    pub(crate) fn frame(&mut self) {
+        self.frame = self.frame.wrapping_add(1);
+        if self.jump_state == JumpState::Jumping && self.frame > 32 {
+            if self.jumps_remaining > 0 {
+                self.jump_state = JumpState::HasJump;
+            } else {
+                self.jump_state = JumpState::Falling;
+            }
+        }
+    }
+
+    pub fn pose(&self) -> Pose {
+        match self.jump_state {
+            JumpState::HasJump => {
+                if self.speed.magnitude_squared() < num!(0.1) {
+                    Pose::Idle
+                } else {
+                    Pose::Walking(self.frame / 8)
+                }
+            }
+            JumpState::Jumping => Pose::Jumping(self.frame / 16),
+            JumpState::Falling => Pose::Falling,
+        }
+    }
+
+    pub fn is_on_ground(&self) -> bool {
+        self.ground_state == GroundState::OnGround
+    }
+
+    pub fn stats(&self) -> SavedStats {
+        SavedStats {
+            ground_speed: self.ground_speed,
+            air_speed: self.air_speed,
+            jump_speed: self.jump_speed,
+            max_jumps: self.max_jumps.min(u8::MAX as usize) as u8,
+            can_dash: self.can_dash,
+        }
+    }
+
+    pub fn set_stats(&mut self, stats: &SavedStats) {
+        self.ground_speed = stats.ground_speed;
+        self.air_speed = stats.air_speed;
+        self.jump_speed = stats.jump_speed;
+        self.max_jumps = stats.max_jumps as usize;
+        self.jumps_remaining = self.max_jumps;
+        self.can_dash = stats.can_dash;
+    }
+
+    pub fn apply_powerup(&mut self, powerup: &PowerUp) {
+        for modifier in powerup.modifiers {
+            let apply = |value| modifier.operation.apply(value, modifier.amount);
+
+            match modifier.stat {
+                PlayerStat::GroundSpeed => self.ground_speed = apply(self.ground_speed),
+                PlayerStat::AirSpeed => self.air_speed = apply(self.air_speed),
+                PlayerStat::JumpSpeed => self.jump_speed = apply(self.jump_speed),
+                PlayerStat::MaxJumps => {
+                    let max_jumps =
+                        apply(Number::new(self.max_jumps as i32)).floor().max(0) as usize;
+
+                    // gaining jumps lets you use them straight away
+                    if max_jumps > self.max_jumps {
+                        self.jumps_remaining += max_jumps - self.max_jumps;
+                    }
+                    self.jumps_remaining = self.jumps_remaining.min(max_jumps);
+                    self.max_jumps = max_jumps;
+                }
+                PlayerStat::CanDash => {
+                    self.can_dash = apply(Number::new(self.can_dash as i32)) != 0.into();
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,61 @@
+use agb_fixnum::Vector2D;
+use map::PowerUp;
+use util::Number;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PowerUpState {
+    Idle,
+    /// The player has touched the power up, and gets it once it has finished animating
+    Collecting,
+}
+
+/// A power up sitting in the level waiting to be collected
+pub struct PowerUpPickup {
+    /// Index of the power up in the level
+    index: usize,
+    pub powerup: &'static PowerUp,
+    /// Frames since the power up last changed state, used for animation
+    pub frame: usize,
+    pub state: PowerUpState,
+}
+
+/// What happened to a power up during a frame
+pub(crate) enum PickupProgress {
+    Nothing,
+    Touched,
+    Collected(&'static PowerUp),
+}
+
+impl PowerUpPickup {
+    pub(crate) fn new(index: usize, powerup: &'static PowerUp) -> Self {
+        Self {
+            index,
+            powerup,
+            frame: 0,
+            state: PowerUpState::Idle,
+        }
+    }
+
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    pub(crate) fn update(&mut self, player_location: Vector2D<Number>) -> PickupProgress {
+        self.frame += 1;
+
+        if self.state == PowerUpState::Collecting && self.frame > 32 {
+            return PickupProgress::Collected(self.powerup);
+        }
+
+        let player_distance_sq = (player_location - self.powerup.location).magnitude_squared();
+
+        if self.state == PowerUpState::Idle && player_distance_sq < (16 * 16).into() {
+            self.state = PowerUpState::Collecting;
+            self.frame = 0;
+
+            return PickupProgress::Touched;
+        }
+
+        PickupProgress::Nothing
+    }
+}
@@ -0,0 +1,390 @@
+use alloc::vec::Vec;
+use util::{
+    fnv1a, seal, unseal, Buttons, InputSnapshot, InputSource, Reader, SaveError, SaveGame,
+    HEADER_SIZE, SLOT_SIZE,
+};
+
+use crate::{PlayerInput, Simulation};
+
+/// How many frames apart the player's position is hashed to check a replay hasn't desynced
+pub const HASH_INTERVAL: u32 = 60;
+
+const MAGIC: [u8; 4] = *b"BTSr";
+/// Bumped whenever the layout of the payload, or anything about the simulation which changes how
+/// inputs play out, changes
+const VERSION: u16 = 2;
+/// The most one frame can add to an encoded recording, a new set of buttons and a hash
+const MAX_FRAME_LEN: usize = 8;
+
+/// Why a replay or ghost couldn't be used
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ReplayError {
+    /// There is no replay or ghost here
+    NotAReplay,
+    /// The replay was recorded by a different version of the game
+    UnsupportedVersion(u16),
+    Corrupt,
+    /// The replay starts on a level which doesn't exist
+    UnknownLevel(u8),
+    Desync(Desync),
+}
+
+impl From<SaveError> for ReplayError {
+    fn from(error: SaveError) -> Self {
+        match error {
+            SaveError::Empty => Self::NotAReplay,
+            SaveError::UnsupportedVersion(version) => Self::UnsupportedVersion(version),
+            SaveError::Corrupt => Self::Corrupt,
+        }
+    }
+}
+
+/// The simulation has ended up somewhere other than it did when the replay was recorded
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Desync {
+    pub frame: u32,
+    pub expected: u32,
+    pub actual: u32,
+}
+
+/// Hashes the parts of the simulation which would drift first if it played out differently
+pub fn position_hash(simulation: &Simulation) -> u32 {
+    let player = simulation.player();
+    let values = [
+        player.position.x,
+        player.position.y,
+        player.speed.x,
+        player.speed.y,
+    ];
+
+    let mut bytes = [0; 16];
+    for (chunk, value) in bytes.chunks_exact_mut(4).zip(values) {
+        chunk.copy_from_slice(&value.to_raw().to_le_bytes());
+    }
+
+    fnv1a(&bytes)
+}
+
+/// The buttons held on every frame of a game, along with where the game started from
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Recording {
+    /// The game the recording starts from
+    pub start: SaveGame,
+    /// The buttons held the frame before the recording starts, so that buttons held since before
+    /// then aren't replayed as presses
+    initial: Buttons,
+    /// Each set of buttons, and how many frames in a row they were held for
+    inputs: Vec<(Buttons, u32)>,
+    /// The position hash every `HASH_INTERVAL` frames
+    hashes: Vec<u32>,
+    frames: u32,
+}
+
+impl Recording {
+    pub fn new(start: SaveGame) -> Self {
+        Self {
+            start,
+            initial: Buttons::NONE,
+            inputs: Vec::new(),
+            hashes: Vec::new(),
+            frames: 0,
+        }
+    }
+
+    /// Records a frame, once the simulation has been moved on with the input
+    pub fn record(&mut self, input: &InputSnapshot, simulation: &Simulation) {
+        if self.frames == 0 {
+            self.initial = input.previous;
+        }
+
+        match self.inputs.last_mut() {
+            Some((buttons, frames)) if *buttons == input.held && *frames < u16::MAX as u32 => {
+                *frames += 1;
+            }
+            _ => self.inputs.push((input.held, 1)),
+        }
+
+        self.frames += 1;
+        if self.frames % HASH_INTERVAL == 0 {
+            self.hashes.push(position_hash(simulation));
+        }
+    }
+
+    /// How many frames have been recorded
+    pub fn frames(&self) -> u32 {
+        self.frames
+    }
+
+    /// How many bytes the recording takes up once it's encoded
+    pub fn encoded_len(&self) -> usize {
+        HEADER_SIZE + SLOT_SIZE + 2 + 4 + 4 + self.inputs.len() * 4 + 4 + self.hashes.len() * 4
+    }
+
+    /// Whether another frame can be recorded without the encoded recording going over `max_len`
+    pub fn has_room(&self, max_len: usize) -> bool {
+        self.encoded_len() + MAX_FRAME_LEN <= max_len
+    }
+
+    /// A new simulation in the state the recording starts from
+    pub fn simulation(&self) -> Result<Simulation, ReplayError> {
+        let level = map::LEVELS
+            .get(self.start.level as usize)
+            .ok_or(ReplayError::UnknownLevel(self.start.level))?;
+
+        let mut simulation = Simulation::new(level);
+        simulation.restore(&self.start);
+        Ok(simulation)
+    }
+
+    pub fn playback(self) -> Playback {
+        Playback {
+            recording: self,
+            step: 0,
+            step_frame: 0,
+            frame: 0,
+        }
+    }
+
+    /// Plays the whole recording through a new simulation, giving the simulation at the end
+    pub fn resimulate(self) -> Result<Simulation, ReplayError> {
+        let mut simulation = self.simulation()?;
+        let mut playback = self.playback();
+        let mut input = playback.initial_snapshot();
+
+        while !playback.is_finished() {
+            input = playback.next_snapshot(input);
+            simulation.frame(&PlayerInput::from(&input));
+            playback.check(&simulation).map_err(ReplayError::Desync)?;
+        }
+
+        Ok(simulation)
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.encoded_len());
+        bytes.resize(HEADER_SIZE, 0);
+        bytes.extend_from_slice(&self.start.encode());
+        bytes.extend_from_slice(&self.initial.0.to_le_bytes());
+        bytes.extend_from_slice(&self.frames.to_le_bytes());
+
+        bytes.extend_from_slice(&(self.inputs.len() as u32).to_le_bytes());
+        for &(buttons, frames) in self.inputs.iter() {
+            bytes.extend_from_slice(&buttons.0.to_le_bytes());
+            bytes.extend_from_slice(&(frames as u16).to_le_bytes());
+        }
+
+        bytes.extend_from_slice(&(self.hashes.len() as u32).to_le_bytes());
+        for hash in self.hashes.iter() {
+            bytes.extend_from_slice(&hash.to_le_bytes());
+        }
+
+        seal(MAGIC, VERSION, &mut bytes);
+        bytes
+    }
+
+    /// Reads a replay from the start of the bytes. Anything after the end of the replay is ignored.
+    pub fn decode(bytes: &[u8]) -> Result<Self, ReplayError> {
+        let payload = unseal(MAGIC, VERSION, bytes)?;
+        Self::decode_payload(&mut Reader::new(payload)).ok_or(ReplayError::Corrupt)
+    }
+
+    fn decode_payload(payload: &mut Reader) -> Option<Self> {
+        let start = SaveGame::decode(&payload.bytes::<SLOT_SIZE>()?).ok()?;
+        let initial = Buttons(payload.u16()?);
+        let frames = payload.u32()?;
+
+        let input_count = payload.u32()?;
+        let inputs = (0..input_count)
+            .map(|_| Some((Buttons(payload.u16()?), payload.u16()? as u32)))
+            .collect::<Option<Vec<_>>>()?;
+
+        let hash_count = payload.u32()?;
+        let hashes = (0..hash_count)
+            .map(|_| payload.u32())
+            .collect::<Option<Vec<_>>>()?;
+
+        Some(Self {
+            start,
+            initial,
+            inputs,
+            hashes,
+            frames,
+        })
+    }
+}
+
+/// Plays back the buttons from a recording, checking the simulation still matches it as it goes
+pub struct Playback {
+    recording: Recording,
+    step: usize,
+    step_frame: u32,
+    /// How many frames have been checked
+    frame: u32,
+}
+
+impl Playback {
+    /// The input to move on from to get the first frame of the recording
+    pub fn initial_snapshot(&self) -> InputSnapshot {
+        InputSnapshot {
+            held: self.recording.initial,
+            previous: Buttons::NONE,
+        }
+    }
+
+    pub fn recording(&self) -> &Recording {
+        &self.recording
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.frame >= self.recording.frames
+    }
+
+    /// Checks the simulation against the recording, once it has been moved on with this frame's
+    /// buttons
+    pub fn check(&mut self, simulation: &Simulation) -> Result<(), Desync> {
+        self.frame += 1;
+        if self.frame % HASH_INTERVAL != 0 {
+            return Ok(());
+        }
+
+        let Some(&expected) = self
+            .recording
+            .hashes
+            .get((self.frame / HASH_INTERVAL) as usize - 1)
+        else {
+            return Ok(());
+        };
+
+        let actual = position_hash(simulation);
+        if actual != expected {
+            return Err(Desync {
+                frame: self.frame,
+                expected,
+                actual,
+            });
+        }
+
+        Ok(())
+    }
+}
+
+impl InputSource for Playback {
+    fn buttons(&mut self) -> Buttons {
+        while let Some(&(buttons, frames)) = self.recording.inputs.get(self.step) {
+            if self.step_frame < frames {
+                self.step_frame += 1;
+                return buttons;
+            }
+
+            self.step += 1;
+            self.step_frame = 0;
+        }
+
+        Buttons::NONE
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use util::ScriptedInput;
+
+    use super::*;
+
+    fn start() -> SaveGame {
+        let simulation = Simulation::new(map::LEVELS[0]);
+
+        SaveGame {
+            level: 0,
+            stats: simulation.player().stats(),
+            collected_power_ups: simulation.collected_power_ups(),
+            seen_mission_logs: Default::default(),
+            recovery_point: None,
+            play_time: 0,
+        }
+    }
+
+    /// Plays a script through a simulation, recording it as it goes
+    fn record(steps: &[(Buttons, u32)]) -> (Recording, Simulation) {
+        let mut recording = Recording::new(start());
+        let mut simulation = recording.simulation().unwrap();
+        let mut script = ScriptedInput::new(steps);
+        let mut input = InputSnapshot::default().next(Buttons::A);
+
+        while !script.is_finished() {
+            input = script.next_snapshot(input);
+            simulation.frame(&PlayerInput::from(&input));
+            recording.record(&input, &simulation);
+        }
+
+        (recording, simulation)
+    }
+
+    /// Lands while still holding the button pressed before recording, then walks and jumps
+    fn steps() -> [(Buttons, u32); 5] {
+        [
+            (Buttons::A, 90),
+            (Buttons::RIGHT, 60),
+            (Buttons::RIGHT | Buttons::A, 20),
+            (Buttons::LEFT, 100),
+            (Buttons::NONE, 30),
+        ]
+    }
+
+    #[test]
+    fn replays_end_up_where_the_recording_did() {
+        let (recording, simulation) = record(&steps());
+        assert_eq!(recording.frames(), 300);
+
+        let replayed = recording.resimulate().unwrap();
+        assert_eq!(replayed.player().position, simulation.player().position);
+        assert_eq!(replayed.player().speed, simulation.player().speed);
+    }
+
+    #[test]
+    fn replays_survive_encoding() {
+        let (recording, _) = record(&steps());
+
+        let mut bytes = recording.encode();
+        bytes.extend_from_slice(&[0xff; 16]);
+
+        assert_eq!(Recording::decode(&bytes), Ok(recording));
+    }
+
+    #[test]
+    fn recordings_can_be_kept_to_a_length() {
+        let max_len = HEADER_SIZE + SLOT_SIZE + 32;
+        let mut recording = Recording::new(start());
+        let mut simulation = recording.simulation().unwrap();
+        let steps = steps();
+        let mut script = ScriptedInput::new(&steps);
+        let mut input = InputSnapshot::default();
+
+        while recording.has_room(max_len) && !script.is_finished() {
+            input = script.next_snapshot(input);
+            simulation.frame(&PlayerInput::from(&input));
+            recording.record(&input, &simulation);
+        }
+
+        assert!(recording.frames() < 300);
+        assert_eq!(recording.encoded_len(), recording.encode().len());
+        assert!(recording.encoded_len() <= max_len);
+    }
+
+    #[test]
+    fn changed_replays_are_caught() {
+        let (recording, _) = record(&steps());
+
+        let mut bytes = recording.encode();
+        *bytes.last_mut().unwrap() ^= 1;
+        assert_eq!(Recording::decode(&bytes), Err(ReplayError::Corrupt));
+        assert_eq!(Recording::decode(&[0xff; 64]), Err(ReplayError::NotAReplay));
+
+        let mut desynced = recording.clone();
+        desynced.inputs[1].1 += 1;
+        desynced.inputs[2].1 -= 1;
+        assert!(matches!(
+            desynced.resimulate(),
+            Err(ReplayError::Desync(Desync { frame: 180, .. }))
+        ));
+    }
+}
@@ -0,0 +1,240 @@
+use agb_fixnum::{Num, Vector2D};
+use alloc::vec::Vec;
+use map::{Body, Level, Path, PathDirection, PathPoint};
+use util::{Collider, Number};
+
+/// Everything near a point which the player could collide with
+#[derive(Copy, Clone)]
+pub(crate) struct DynamicAndStaticColliders<'a> {
+    static_colliders: &'static [&'static Collider],
+    dynamic_colliders: &'a [DynamicCollider],
+}
+
+impl<'a> DynamicAndStaticColliders<'a> {
+    /// Gives the moving body each collider belongs to, along with its index in that body
+    pub(crate) fn iter_with_body(
+        &self,
+    ) -> impl Iterator<Item = (&'a Collider, Option<(&'a DynamicCollider, usize)>)> + Clone {
+        self.static_colliders.iter().map(|&x| (x, None)).chain(
+            self.dynamic_colliders.iter().flat_map(|dynamic_collider| {
+                dynamic_collider
+                    .colliders
+                    .iter()
+                    .enumerate()
+                    .map(move |(idx, x)| (x, Some((dynamic_collider, idx))))
+            }),
+        )
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.static_colliders.is_empty()
+            && !self
+                .dynamic_colliders
+                .iter()
+                .flat_map(|x| x.colliders.iter())
+                .any(|x| x.tag.is_gravitational())
+    }
+}
+
+/// A body which moves along a path, along with its colliders
+pub struct DynamicCollider {
+    path: &'static Path,
+    pub body: &'static Body,
+    current_path_element_idx: usize,
+    pub current_position: Vector2D<Number>,
+    pub(crate) colliders: Vec<Collider>,
+    direction: PathDirection,
+    path_index_timer: Num<i32, 24>,
+    remaining_pause: u16,
+    finished: bool,
+    // how far the body moved this frame
+    pub velocity: Vector2D<Number>,
+}
+
+impl DynamicCollider {
+    fn new(path: &'static Path, body: &'static Body) -> Self {
+        let mut dynamic_collider = Self {
+            path,
+            body,
+            current_path_element_idx: body.start.index,
+            current_position: path.points[0].point,
+            colliders: body.colliders.to_vec(),
+            direction: body.start.direction,
+            path_index_timer: body.start.timer,
+            remaining_pause: body.start.pause,
+            finished: body.start.finished,
+            velocity: (0, 0).into(),
+        };
+
+        // the colliders are placed at the start of the path, so move them to where the path starts
+        let (from, to) = dynamic_collider.current_segment();
+        let start_position = dynamic_collider.position_along_segment(from, to);
+        dynamic_collider.move_to(start_position);
+
+        dynamic_collider
+    }
+
+    fn next_path_element_idx(&self) -> usize {
+        let len = self.path.points.len();
+        match self.direction {
+            PathDirection::Forwards => (self.current_path_element_idx + 1) % len,
+            PathDirection::Backwards => {
+                (self.current_path_element_idx as isize - 1).rem_euclid(len as isize) as usize
+            }
+        }
+    }
+
+    fn current_segment(&self) -> (&'static PathPoint, &'static PathPoint) {
+        (
+            &self.path.points[self.current_path_element_idx],
+            &self.path.points[self.next_path_element_idx()],
+        )
+    }
+
+    fn position_along_segment(&self, from: &PathPoint, to: &PathPoint) -> Vector2D<Number> {
+        let progress = self.path.easing.apply(self.path_index_timer);
+        from.point * (-progress + 1).change_base() + to.point * progress.change_base()
+    }
+
+    fn move_to(&mut self, position: Vector2D<Number>) {
+        let velocity = position - self.current_position;
+        self.velocity = velocity;
+        self.current_position = position;
+        for collider in self.colliders.iter_mut() {
+            collider.apply_velocity(velocity);
+        }
+    }
+
+    fn arrive_at_next_point(&mut self) {
+        let len = self.path.points.len();
+        self.current_path_element_idx = self.next_path_element_idx();
+        self.remaining_pause = self.path.points[self.current_path_element_idx].pause;
+
+        match self.direction {
+            PathDirection::Forwards => {
+                if !self.path.complete {
+                    if self.current_path_element_idx == len - 1 {
+                        if self.path.one_shot {
+                            self.finished = true;
+                        } else {
+                            self.direction = PathDirection::Backwards;
+                        }
+                    }
+                } else if self.current_path_element_idx == 0 && self.path.one_shot {
+                    self.finished = true;
+                }
+            }
+            PathDirection::Backwards => {
+                if self.current_path_element_idx == 0 {
+                    self.direction = PathDirection::Forwards;
+                }
+            }
+        }
+    }
+
+    fn update(&mut self) {
+        if self.remaining_pause > 0 || self.finished {
+            self.remaining_pause = self.remaining_pause.saturating_sub(1);
+            self.move_to(self.current_position);
+            return;
+        }
+
+        let (from, to) = self.current_segment();
+        let frames = match self.direction {
+            PathDirection::Forwards => from.incrementer,
+            PathDirection::Backwards => to.incrementer,
+        };
+
+        self.path_index_timer += frames;
+        if self.path_index_timer >= 1.into() {
+            self.path_index_timer -= 1;
+            self.move_to(to.point);
+            self.arrive_at_next_point();
+
+            if self.finished {
+                self.path_index_timer = 0.into();
+            }
+        } else {
+            let next_position = self.position_along_segment(from, to);
+            self.move_to(next_position);
+        }
+    }
+}
+
+/// The level's colliders, including the moving bodies near the player
+pub struct Terrain {
+    level: &'static Level,
+    loaded_dynamic_colliders: Vec<DynamicCollider>,
+}
+
+impl Terrain {
+    pub fn new(level: &'static Level) -> Self {
+        Self {
+            level,
+            loaded_dynamic_colliders: Vec::new(),
+        }
+    }
+
+    /// The moving bodies which are near enough to the player to be moving
+    pub fn dynamic_colliders(&self) -> &[DynamicCollider] {
+        &self.loaded_dynamic_colliders
+    }
+
+    pub(crate) fn colliders(&self, position: Vector2D<Number>) -> DynamicAndStaticColliders {
+        DynamicAndStaticColliders {
+            static_colliders: self
+                .level
+                .get_nearby(position.x.floor(), position.y.floor()),
+            dynamic_colliders: &self.loaded_dynamic_colliders,
+        }
+    }
+
+    pub(crate) fn dynamic_collider(&self, body: &'static Body) -> Option<&DynamicCollider> {
+        self.loaded_dynamic_colliders
+            .iter()
+            .find(|x| core::ptr::eq(x.body, body))
+    }
+
+    fn load_paths(&mut self, player_position: Vector2D<Number>) {
+        let should_be_loaded_paths = self
+            .level
+            .get_paths(player_position.x.floor(), player_position.y.floor());
+        // remove non active paths
+        self.loaded_dynamic_colliders.retain(|x| {
+            should_be_loaded_paths
+                .iter()
+                .any(|&p| core::ptr::eq(x.path, p))
+        });
+        let paths_to_load: Vec<_> = should_be_loaded_paths
+            .iter()
+            .copied()
+            .filter(|&path| {
+                !self
+                    .loaded_dynamic_colliders
+                    .iter()
+                    .any(|x| core::ptr::eq(x.path, path))
+            })
+            .collect();
+
+        // load now active paths
+        for to_be_loaded in paths_to_load {
+            self.loaded_dynamic_colliders.extend(
+                to_be_loaded
+                    .bodies
+                    .iter()
+                    .map(|body| DynamicCollider::new(to_be_loaded, body)),
+            );
+        }
+    }
+
+    fn update_paths(&mut self) {
+        for loaded in self.loaded_dynamic_colliders.iter_mut() {
+            loaded.update();
+        }
+    }
+
+    pub(crate) fn update(&mut self, player_position: Vector2D<Number>) {
+        self.load_paths(player_position);
+        self.update_paths();
+    }
+}
@@ -0,0 +1,95 @@
+//! Plays a replay recorded on the GBA back through the simulation on the host, to check that it
+//! still plays out the same against the current map and physics.
+//!
+//! Takes a replay dumped to the mGBA log, a save file, or the raw replay bytes.
+
+use std::{error::Error, process::ExitCode};
+
+use physics::{Recording, ReplayError};
+use util::REPLAY_OFFSET;
+
+/// Written before the hex of each replay in the mGBA log
+const LOG_START: &str = "replay start";
+/// Written before each line of hex in the mGBA log
+const LOG_LINE: &str = "replay: ";
+
+fn main() -> Result<ExitCode, Box<dyn Error>> {
+    let path = std::env::args()
+        .nth(1)
+        .ok_or("Usage: replay <mGBA log, save file or replay>")?;
+    let contents = std::fs::read(&path)?;
+
+    let replay = find_replay(&contents).ok_or("Couldn't find a replay in the file")?;
+    let recording = Recording::decode(&replay).map_err(|error| format!("{error:?}"))?;
+    let frames = recording.frames();
+    let level = recording.start.level;
+
+    match recording.resimulate() {
+        Ok(simulation) => {
+            let position = simulation.player().position;
+            println!(
+                "Replayed {frames} frames on level {level}, ending at ({}, {})",
+                position.x, position.y
+            );
+            Ok(ExitCode::SUCCESS)
+        }
+        Err(ReplayError::Desync(desync)) => {
+            eprintln!(
+                "Desynced on frame {} of {frames}: expected hash {:08x} but got {:08x}",
+                desync.frame, desync.expected, desync.actual
+            );
+            Ok(ExitCode::FAILURE)
+        }
+        Err(error) => Err(format!("{error:?}").into()),
+    }
+}
+
+/// The replay bytes, from whichever kind of file they're in
+fn find_replay(contents: &[u8]) -> Option<Vec<u8>> {
+    if Recording::decode(contents) != Err(ReplayError::NotAReplay) {
+        return Some(contents.to_vec());
+    }
+
+    if let Some(save) = contents.get(REPLAY_OFFSET..) {
+        if Recording::decode(save) != Err(ReplayError::NotAReplay) {
+            return Some(save.to_vec());
+        }
+    }
+
+    from_log(std::str::from_utf8(contents).ok()?)
+}
+
+/// The last replay written to the log
+fn from_log(log: &str) -> Option<Vec<u8>> {
+    let start = log.rfind(LOG_START)?;
+
+    log[start..]
+        .lines()
+        .filter_map(|line| line.split_once(LOG_LINE).map(|(_, hex)| hex.trim()))
+        .flat_map(|hex| {
+            hex.as_bytes()
+                .chunks(2)
+                .map(|byte| u8::from_str_radix(std::str::from_utf8(byte).ok()?, 16).ok())
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_the_last_replay_in_the_log() {
+        let log = "\
+[INFO] GBA Debug: replay start
+[INFO] GBA Debug: replay: 0001
+[INFO] GBA Debug: something else
+[INFO] GBA Debug: replay start
+[INFO] GBA Debug: replay: 0a0b
+[INFO] GBA Debug: replay: ff
+";
+
+        assert_eq!(from_log(log), Some(vec![0x0a, 0x0b, 0xff]));
+        assert_eq!(from_log("replay: 00"), None);
+    }
+}
@@ -0,0 +1,118 @@
+use agb_fixnum::Vector2D;
+
+use crate::Number;
+
+/// A position in the level
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct RealSpace(pub Vector2D<Number>);
+
+/// A position on the screen, relative to its top left corner
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct ScreenSpace(pub Vector2D<Number>);
+
+impl ScreenSpace {
+    /// The pixel this position is in
+    pub fn pixel(self) -> Vector2D<i32> {
+        self.0.floor()
+    }
+}
+
+/// Converts between positions in the level and positions on the screen for a camera.
+///
+/// The camera is snapped to a whole pixel, so that everything drawn with the same transform moves
+/// together, whether it's a background or a sprite.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct CameraTransform {
+    top_left: Vector2D<i32>,
+    screen_size: Vector2D<i32>,
+}
+
+impl CameraTransform {
+    /// A camera centred on `centre`, looking at a screen of `screen_size` pixels
+    pub fn new(centre: RealSpace, screen_size: Vector2D<i32>) -> Self {
+        let rounded_centre =
+            (centre.0 + Vector2D::new(Number::new(1) / 2, Number::new(1) / 2)).floor();
+
+        Self {
+            top_left: rounded_centre - screen_size / 2,
+            screen_size,
+        }
+    }
+
+    /// The position in the level of the top left of the screen, which is where the backgrounds
+    /// should be scrolled to
+    pub fn top_left(&self) -> Vector2D<i32> {
+        self.top_left
+    }
+
+    pub fn to_screen(&self, position: RealSpace) -> ScreenSpace {
+        ScreenSpace(position.0 - self.top_left.change_base())
+    }
+
+    pub fn to_real(&self, position: ScreenSpace) -> RealSpace {
+        RealSpace(position.0 + self.top_left.change_base())
+    }
+
+    /// Whether the position is on the screen, or within `margin` pixels of it
+    pub fn is_on_screen(&self, position: RealSpace, margin: i32) -> bool {
+        let position = self.to_screen(position).pixel();
+
+        (-margin..self.screen_size.x + margin).contains(&position.x)
+            && (-margin..self.screen_size.y + margin).contains(&position.y)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCREEN_SIZE: Vector2D<i32> = Vector2D::new(240, 160);
+
+    fn camera(x: Number, y: Number) -> CameraTransform {
+        CameraTransform::new(RealSpace((x, y).into()), SCREEN_SIZE)
+    }
+
+    #[test]
+    fn centre_of_the_camera_is_the_centre_of_the_screen() {
+        let camera = camera(100.into(), 50.into());
+
+        assert_eq!(camera.top_left(), (-20, -30).into());
+        assert_eq!(
+            camera.to_screen(RealSpace((100, 50).into())).pixel(),
+            (120, 80).into()
+        );
+    }
+
+    #[test]
+    fn sprites_move_with_the_background() {
+        // a sprite on a whole pixel should stay on the same background pixel, however the camera
+        // is positioned within a pixel
+        for sub_pixel in 0..256 {
+            let camera = camera(Number::from_raw(100 * 256 + sub_pixel), 50.into());
+            let sprite = RealSpace((37, 12).into());
+
+            assert_eq!(
+                camera.to_screen(sprite).pixel() + camera.top_left(),
+                (37, 12).into(),
+            );
+        }
+    }
+
+    #[test]
+    fn converts_back_to_the_same_position() {
+        let camera = camera(Number::from_raw(1234), Number::from_raw(-5678));
+        let position = RealSpace((Number::from_raw(99), Number::from_raw(-3)).into());
+
+        assert_eq!(camera.to_real(camera.to_screen(position)), position);
+    }
+
+    #[test]
+    fn margin_extends_the_screen() {
+        let camera = camera(120.into(), 80.into());
+
+        assert!(camera.is_on_screen(RealSpace((0, 0).into()), 0));
+        assert!(!camera.is_on_screen(RealSpace((-8, 0).into()), 0));
+        assert!(camera.is_on_screen(RealSpace((-8, 0).into()), 16));
+        assert!(!camera.is_on_screen(RealSpace((240, 0).into()), 0));
+    }
+}
@@ -0,0 +1,130 @@
+use crate::{Number, SaveError};
+
+/// The magic, version, payload length and checksum
+pub const HEADER_SIZE: usize = 12;
+
+/// Fills in the header at the start of the bytes for the payload which follows it, so that it can
+/// be told apart from other data and checked for corruption when it's read back
+pub fn seal(magic: [u8; 4], version: u16, bytes: &mut [u8]) {
+    let (header, payload) = bytes.split_at_mut(HEADER_SIZE);
+    let length = u16::try_from(payload.len()).expect("Payload should fit in 64KiB");
+
+    header[..4].copy_from_slice(&magic);
+    header[4..6].copy_from_slice(&version.to_le_bytes());
+    header[6..8].copy_from_slice(&length.to_le_bytes());
+    header[8..].copy_from_slice(&checksum(version, payload).to_le_bytes());
+}
+
+/// The payload sealed at the start of the bytes. Anything after the end of it is ignored.
+pub fn unseal(magic: [u8; 4], version: u16, bytes: &[u8]) -> Result<&[u8], SaveError> {
+    let mut header = Reader::new(bytes);
+    if header.bytes::<4>() != Some(magic) {
+        return Err(SaveError::Empty);
+    }
+
+    let found_version = header.u16().ok_or(SaveError::Corrupt)?;
+    if found_version != version {
+        return Err(SaveError::UnsupportedVersion(found_version));
+    }
+
+    let length = header.u16().ok_or(SaveError::Corrupt)? as usize;
+    let expected_checksum = header.u32().ok_or(SaveError::Corrupt)?;
+    let payload = header.take(length).ok_or(SaveError::Corrupt)?;
+    if checksum(version, payload) != expected_checksum {
+        return Err(SaveError::Corrupt);
+    }
+
+    Ok(payload)
+}
+
+/// Hashes over the version and payload
+fn checksum(version: u16, payload: &[u8]) -> u32 {
+    fnv1a(version.to_le_bytes().iter().chain(payload))
+}
+
+/// The 32 bit FNV-1a hash, which is quick to work out a byte at a time and good enough for
+/// spotting corruption
+pub fn fnv1a<'a>(bytes: impl IntoIterator<Item = &'a u8>) -> u32 {
+    bytes.into_iter().fold(0x811c_9dc5, |hash, &byte| {
+        (hash ^ byte as u32).wrapping_mul(0x0100_0193)
+    })
+}
+
+/// Reads little endian values from the front of some bytes
+pub struct Reader<'a> {
+    buffer: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(buffer: &'a [u8]) -> Self {
+        Self {
+            buffer,
+            position: 0,
+        }
+    }
+
+    /// The bytes which haven't been read yet
+    pub fn rest(&self) -> &'a [u8] {
+        &self.buffer[self.position..]
+    }
+
+    pub fn take(&mut self, length: usize) -> Option<&'a [u8]> {
+        let bytes = self.rest().get(..length)?;
+        self.position += length;
+
+        Some(bytes)
+    }
+
+    pub fn bytes<const N: usize>(&mut self) -> Option<[u8; N]> {
+        self.take(N)?.try_into().ok()
+    }
+
+    pub fn u8(&mut self) -> Option<u8> {
+        self.bytes::<1>().map(|[value]| value)
+    }
+
+    pub fn bool(&mut self) -> Option<bool> {
+        match self.u8()? {
+            0 => Some(false),
+            1 => Some(true),
+            _ => None,
+        }
+    }
+
+    pub fn u16(&mut self) -> Option<u16> {
+        self.bytes().map(u16::from_le_bytes)
+    }
+
+    pub fn u32(&mut self) -> Option<u32> {
+        self.bytes().map(u32::from_le_bytes)
+    }
+
+    pub fn number(&mut self) -> Option<Number> {
+        self.bytes()
+            .map(|bytes| Number::from_raw(i32::from_le_bytes(bytes)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sealed_payloads_are_found_again_at_the_start_of_the_bytes() {
+        let mut bytes = [0xff; HEADER_SIZE + 8];
+        bytes[HEADER_SIZE..][..5].copy_from_slice(b"hello");
+        seal(*b"test", 3, &mut bytes[..HEADER_SIZE + 5]);
+
+        assert_eq!(unseal(*b"test", 3, &bytes), Ok(&b"hello"[..]));
+        assert_eq!(unseal(*b"tost", 3, &bytes), Err(SaveError::Empty));
+        assert_eq!(
+            unseal(*b"test", 4, &bytes),
+            Err(SaveError::UnsupportedVersion(3))
+        );
+        assert_eq!(
+            unseal(*b"test", 3, &bytes[..HEADER_SIZE + 4]),
+            Err(SaveError::Corrupt)
+        );
+    }
+}
@@ -0,0 +1,201 @@
+/// A set of GBA buttons, using the same bits as the button register
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug, Hash)]
+pub struct Buttons(pub u16);
+
+impl Buttons {
+    pub const NONE: Buttons = Buttons(0);
+    pub const A: Buttons = Buttons(1 << 0);
+    pub const B: Buttons = Buttons(1 << 1);
+    pub const SELECT: Buttons = Buttons(1 << 2);
+    pub const START: Buttons = Buttons(1 << 3);
+    pub const RIGHT: Buttons = Buttons(1 << 4);
+    pub const LEFT: Buttons = Buttons(1 << 5);
+    pub const UP: Buttons = Buttons(1 << 6);
+    pub const DOWN: Buttons = Buttons(1 << 7);
+    pub const R: Buttons = Buttons(1 << 8);
+    pub const L: Buttons = Buttons(1 << 9);
+
+    /// Every button on the GBA
+    pub const ALL: [Buttons; 10] = [
+        Self::A,
+        Self::B,
+        Self::SELECT,
+        Self::START,
+        Self::RIGHT,
+        Self::LEFT,
+        Self::UP,
+        Self::DOWN,
+        Self::R,
+        Self::L,
+    ];
+
+    pub fn contains(self, buttons: Buttons) -> bool {
+        self.0 & buttons.0 == buttons.0
+    }
+}
+
+impl core::ops::BitOr for Buttons {
+    type Output = Buttons;
+
+    fn bitor(self, rhs: Buttons) -> Buttons {
+        Buttons(self.0 | rhs.0)
+    }
+}
+
+/// The buttons held down during a frame, along with those held during the frame before so that
+/// presses can be told apart from holds
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub struct InputSnapshot {
+    pub held: Buttons,
+    pub previous: Buttons,
+}
+
+impl InputSnapshot {
+    /// The snapshot for the frame after this one
+    pub fn next(self, held: Buttons) -> InputSnapshot {
+        InputSnapshot {
+            held,
+            previous: self.held,
+        }
+    }
+
+    pub fn is_pressed(&self, button: Buttons) -> bool {
+        self.held.contains(button)
+    }
+
+    pub fn is_just_pressed(&self, button: Buttons) -> bool {
+        self.held.contains(button) && !self.previous.contains(button)
+    }
+
+    /// -1 for left, 1 for right and 0 for neither or both
+    pub fn x_tri(&self) -> i32 {
+        self.is_pressed(Buttons::RIGHT) as i32 - self.is_pressed(Buttons::LEFT) as i32
+    }
+
+    /// Like [`x_tri`](Self::x_tri), but only for buttons pressed this frame
+    pub fn just_pressed_x_tri(&self) -> i32 {
+        self.is_just_pressed(Buttons::RIGHT) as i32 - self.is_just_pressed(Buttons::LEFT) as i32
+    }
+}
+
+/// Somewhere the buttons for each frame come from, whether that's the player, a recording or a
+/// test script
+pub trait InputSource {
+    /// The buttons held down for the next frame
+    fn buttons(&mut self) -> Buttons;
+
+    /// Moves on to the next frame
+    fn next_snapshot(&mut self, previous: InputSnapshot) -> InputSnapshot {
+        previous.next(self.buttons())
+    }
+}
+
+/// Holds buttons down for a number of frames each, then lets go of everything
+pub struct ScriptedInput<'a> {
+    steps: &'a [(Buttons, u32)],
+    step: usize,
+    frame: u32,
+}
+
+impl<'a> ScriptedInput<'a> {
+    /// Each step is the buttons to hold and how many frames to hold them for
+    pub fn new(steps: &'a [(Buttons, u32)]) -> Self {
+        Self {
+            steps,
+            step: 0,
+            frame: 0,
+        }
+    }
+
+    /// Whether every scripted frame has been handed out
+    pub fn is_finished(&self) -> bool {
+        let mut remaining = self.steps.iter().skip(self.step).map(|&(_, frames)| frames);
+        let current = remaining.next().unwrap_or(0);
+
+        current <= self.frame && remaining.all(|frames| frames == 0)
+    }
+}
+
+impl InputSource for ScriptedInput<'_> {
+    fn buttons(&mut self) -> Buttons {
+        while let Some(&(buttons, frames)) = self.steps.get(self.step) {
+            if self.frame < frames {
+                self.frame += 1;
+                return buttons;
+            }
+
+            self.step += 1;
+            self.frame = 0;
+        }
+
+        Buttons::NONE
+    }
+}
+
+/// The buttons the player is pressing on the GBA
+#[cfg(feature = "agb")]
+pub struct LiveInput {
+    controller: agb::input::ButtonController,
+}
+
+#[cfg(feature = "agb")]
+impl LiveInput {
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> Self {
+        Self {
+            controller: agb::input::ButtonController::new(),
+        }
+    }
+}
+
+#[cfg(feature = "agb")]
+impl InputSource for LiveInput {
+    fn buttons(&mut self) -> Buttons {
+        self.controller.update();
+
+        Buttons::ALL
+            .into_iter()
+            .filter(|button| {
+                self.controller
+                    .is_pressed(agb::input::Button::from_bits_truncate(button.0 as u32))
+            })
+            .fold(Buttons::NONE, |held, button| held | button)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn holding_a_button_is_only_a_press_on_the_first_frame() {
+        let first = InputSnapshot::default().next(Buttons::A);
+        let second = first.next(Buttons::A | Buttons::LEFT);
+
+        assert!(first.is_just_pressed(Buttons::A));
+        assert!(second.is_pressed(Buttons::A));
+        assert!(!second.is_just_pressed(Buttons::A));
+        assert!(second.is_just_pressed(Buttons::LEFT));
+        assert_eq!(second.x_tri(), -1);
+    }
+
+    #[test]
+    fn scripts_hold_each_step_for_its_frames() {
+        let steps = [(Buttons::RIGHT, 2), (Buttons::NONE, 0), (Buttons::A, 1)];
+        let mut script = ScriptedInput::new(&steps);
+
+        let mut snapshot = InputSnapshot::default();
+        let mut frames = [InputSnapshot::default(); 4];
+        for frame in frames.iter_mut() {
+            snapshot = script.next_snapshot(snapshot);
+            *frame = snapshot;
+        }
+
+        assert_eq!(
+            frames.map(|frame| frame.held),
+            [Buttons::RIGHT, Buttons::RIGHT, Buttons::A, Buttons::NONE]
+        );
+        assert!(frames[2].is_just_pressed(Buttons::A));
+        assert!(script.is_finished());
+    }
+}
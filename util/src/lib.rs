@@ -2,6 +2,27 @@
 
 use agb_fixnum::{Num, Vector2D};
 
+mod camera;
+mod container;
+mod input;
+mod save;
+mod scroll_stop;
+mod solver;
+
+pub use camera::{CameraTransform, RealSpace, ScreenSpace};
+pub use container::{fnv1a, seal, unseal, Reader, HEADER_SIZE};
+#[cfg(feature = "agb")]
+pub use input::LiveInput;
+pub use input::{Buttons, InputSnapshot, InputSource, ScriptedInput};
+pub use save::{
+    slot_offset, ItemSet, SaveError, SaveGame, SavedStats, MAX_SAVED_ITEMS, REPLAY_OFFSET,
+    SAVE_SLOTS, SLOT_SIZE,
+};
+pub use scroll_stop::{ScrollSpring, ScrollStop};
+pub use solver::{
+    resolve_collisions, Contact, ContactManifold, Resolution, MAX_CONTACTS, SOLVER_ITERATIONS,
+};
+
 pub type Number = Num<i32, 8>;
 
 #[derive(Clone, Debug)]
@@ -86,12 +107,6 @@ impl Collider {
     }
 }
 
-#[derive(Clone, Copy, PartialEq, Eq, Debug)]
-pub struct RealSpace(pub Vector2D<Number>);
-
-#[derive(Clone, Copy, PartialEq, Eq, Debug)]
-pub struct ScreenSpace(pub Vector2D<Number>);
-
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub struct Circle {
     pub position: Vector2D<Number>,
@@ -225,14 +240,6 @@ impl Arc {
     }
 }
 
-#[derive(Default, Debug)]
-pub struct ScrollStop {
-    pub minimum_x: Option<Number>,
-    pub minimum_y: Option<Number>,
-    pub maximum_x: Option<Number>,
-    pub maximum_y: Option<Number>,
-}
-
 #[cfg(test)]
 mod tests {
     extern crate std;
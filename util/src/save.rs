@@ -0,0 +1,262 @@
+use agb_fixnum::Vector2D;
+
+use crate::{
+    container::{seal, unseal, Reader, HEADER_SIZE},
+    Number,
+};
+
+/// How many games can be saved at once
+pub const SAVE_SLOTS: usize = 3;
+/// The space each slot takes up in save memory, which leaves room for the layout to grow
+pub const SLOT_SIZE: usize = 256;
+/// The most power ups or mission logs a level can have for them all to be saved
+pub const MAX_SAVED_ITEMS: usize = 128;
+
+const MAGIC: [u8; 4] = *b"BTSc";
+/// Bumped whenever the layout of the payload changes, so that older saves aren't misread
+const VERSION: u16 = 1;
+
+/// Where the replay is kept in save memory, after all the slots
+pub const REPLAY_OFFSET: usize = SAVE_SLOTS * SLOT_SIZE;
+
+/// Where a slot starts in save memory
+pub fn slot_offset(slot: usize) -> usize {
+    assert!(slot < SAVE_SLOTS, "Save slot {slot} doesn't exist");
+    slot * SLOT_SIZE
+}
+
+/// Which of a level's power ups or mission logs have been seen, by their index in the level
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct ItemSet([u32; MAX_SAVED_ITEMS / 32]);
+
+impl ItemSet {
+    pub fn insert(&mut self, idx: usize) {
+        assert!(
+            idx < MAX_SAVED_ITEMS,
+            "Only {MAX_SAVED_ITEMS} items per level can be saved"
+        );
+        self.0[idx / 32] |= 1 << (idx % 32);
+    }
+
+    fn decode(payload: &mut Reader) -> Option<Self> {
+        let mut items = Self::default();
+        for word in items.0.iter_mut() {
+            *word = payload.u32()?;
+        }
+
+        Some(items)
+    }
+
+    pub fn contains(&self, idx: usize) -> bool {
+        idx < MAX_SAVED_ITEMS && self.0[idx / 32] & (1 << (idx % 32)) != 0
+    }
+}
+
+/// The player's stats, as changed by the power ups they've collected
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct SavedStats {
+    pub ground_speed: Number,
+    pub air_speed: Number,
+    pub jump_speed: Number,
+    pub max_jumps: u8,
+    pub can_dash: bool,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct SaveGame {
+    /// Index of the level in the list of levels
+    pub level: u8,
+    pub stats: SavedStats,
+    pub collected_power_ups: ItemSet,
+    pub seen_mission_logs: ItemSet,
+    /// Where the player last recovered to, which is where they carry on from
+    pub recovery_point: Option<Vector2D<Number>>,
+    /// In frames
+    pub play_time: u32,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SaveError {
+    /// Nothing has been saved here
+    Empty,
+    /// It was saved by a version of the game which lays it out differently
+    UnsupportedVersion(u16),
+    /// It doesn't match its checksum, for example because the power went off while saving
+    Corrupt,
+}
+
+impl SaveGame {
+    /// Lays the game out as it is stored in a slot
+    pub fn encode(&self) -> [u8; SLOT_SIZE] {
+        let mut slot = [0; SLOT_SIZE];
+
+        let mut payload = Writer::new(&mut slot[HEADER_SIZE..]);
+        payload.u8(self.level);
+        payload.number(self.stats.ground_speed);
+        payload.number(self.stats.air_speed);
+        payload.number(self.stats.jump_speed);
+        payload.u8(self.stats.max_jumps);
+        payload.u8(self.stats.can_dash as u8);
+        payload.item_set(&self.collected_power_ups);
+        payload.item_set(&self.seen_mission_logs);
+        match self.recovery_point {
+            Some(point) => {
+                payload.u8(1);
+                payload.number(point.x);
+                payload.number(point.y);
+            }
+            None => payload.u8(0),
+        }
+        payload.u32(self.play_time);
+
+        let length = payload.position;
+        seal(MAGIC, VERSION, &mut slot[..HEADER_SIZE + length]);
+
+        slot
+    }
+
+    pub fn decode(slot: &[u8; SLOT_SIZE]) -> Result<Self, SaveError> {
+        let payload = unseal(MAGIC, VERSION, slot)?;
+        Self::decode_payload(&mut Reader::new(payload)).ok_or(SaveError::Corrupt)
+    }
+
+    fn decode_payload(payload: &mut Reader) -> Option<Self> {
+        Some(Self {
+            level: payload.u8()?,
+            stats: SavedStats {
+                ground_speed: payload.number()?,
+                air_speed: payload.number()?,
+                jump_speed: payload.number()?,
+                max_jumps: payload.u8()?,
+                can_dash: payload.bool()?,
+            },
+            collected_power_ups: ItemSet::decode(payload)?,
+            seen_mission_logs: ItemSet::decode(payload)?,
+            recovery_point: match payload.bool()? {
+                true => Some((payload.number()?, payload.number()?).into()),
+                false => None,
+            },
+            play_time: payload.u32()?,
+        })
+    }
+}
+
+struct Writer<'a> {
+    buffer: &'a mut [u8],
+    position: usize,
+}
+
+impl<'a> Writer<'a> {
+    fn new(buffer: &'a mut [u8]) -> Self {
+        Self {
+            buffer,
+            position: 0,
+        }
+    }
+
+    fn bytes(&mut self, bytes: &[u8]) {
+        self.buffer[self.position..][..bytes.len()].copy_from_slice(bytes);
+        self.position += bytes.len();
+    }
+
+    fn u8(&mut self, value: u8) {
+        self.bytes(&[value]);
+    }
+
+    fn u32(&mut self, value: u32) {
+        self.bytes(&value.to_le_bytes());
+    }
+
+    fn number(&mut self, value: Number) {
+        self.bytes(&value.to_raw().to_le_bytes());
+    }
+
+    fn item_set(&mut self, items: &ItemSet) {
+        for word in items.0 {
+            self.u32(word);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn save_game() -> SaveGame {
+        let mut collected_power_ups = ItemSet::default();
+        collected_power_ups.insert(0);
+        collected_power_ups.insert(100);
+
+        let mut seen_mission_logs = ItemSet::default();
+        seen_mission_logs.insert(3);
+
+        SaveGame {
+            level: 2,
+            stats: SavedStats {
+                ground_speed: Number::new(1) / 4,
+                air_speed: Number::new(1) / 16,
+                jump_speed: Number::from_raw(563),
+                max_jumps: 2,
+                can_dash: true,
+            },
+            collected_power_ups,
+            seen_mission_logs,
+            recovery_point: Some((Number::new(-120), Number::from_raw(9001)).into()),
+            play_time: 60 * 60 * 90,
+        }
+    }
+
+    #[test]
+    fn saves_load_back_the_same() {
+        let game = save_game();
+
+        assert_eq!(SaveGame::decode(&game.encode()), Ok(game));
+
+        let without_recovery = SaveGame {
+            recovery_point: None,
+            ..game
+        };
+        assert_eq!(
+            SaveGame::decode(&without_recovery.encode()),
+            Ok(without_recovery)
+        );
+    }
+
+    #[test]
+    fn blank_save_memory_is_empty() {
+        assert_eq!(SaveGame::decode(&[0xff; SLOT_SIZE]), Err(SaveError::Empty));
+        assert_eq!(SaveGame::decode(&[0; SLOT_SIZE]), Err(SaveError::Empty));
+    }
+
+    #[test]
+    fn damaged_saves_are_corrupt() {
+        let mut slot = save_game().encode();
+        slot[HEADER_SIZE + 5] ^= 0x10;
+
+        assert_eq!(SaveGame::decode(&slot), Err(SaveError::Corrupt));
+    }
+
+    #[test]
+    fn other_versions_are_not_read() {
+        let mut slot = save_game().encode();
+        slot[4..6].copy_from_slice(&(VERSION + 1).to_le_bytes());
+
+        assert_eq!(
+            SaveGame::decode(&slot),
+            Err(SaveError::UnsupportedVersion(VERSION + 1))
+        );
+    }
+
+    #[test]
+    fn item_sets_hold_every_index() {
+        let mut items = ItemSet::default();
+        for idx in (0..MAX_SAVED_ITEMS).step_by(7) {
+            items.insert(idx);
+        }
+
+        for idx in 0..MAX_SAVED_ITEMS {
+            assert_eq!(items.contains(idx), idx % 7 == 0, "item {idx}");
+        }
+        assert!(!items.contains(MAX_SAVED_ITEMS));
+    }
+}
@@ -0,0 +1,211 @@
+use agb_fixnum::Vector2D;
+
+use crate::Number;
+
+/// The furthest the camera's centre can go in each direction, so that the screen doesn't scroll
+/// past a scroll stop
+#[derive(Default, Debug)]
+pub struct ScrollStop {
+    pub minimum_x: Option<Number>,
+    pub minimum_y: Option<Number>,
+    pub maximum_x: Option<Number>,
+    pub maximum_y: Option<Number>,
+}
+
+/// How the camera is eased back to a scroll stop's limit when it is past it, for example after
+/// coming into the stop's area from outside
+#[derive(Clone, Copy, Debug)]
+pub struct ScrollSpring {
+    /// How much of the distance to the limit is added to the camera's speed each frame
+    pub stiffness: Number,
+    /// How much of the camera's speed towards the limit is lost each frame
+    pub damping: Number,
+    /// Cameras further past a limit than this are on the other side of the stop, so are left to
+    /// move freely. This is usually half the size of the screen, which is how far past the limit
+    /// the camera can be while the line the stop was drawn with is still on screen.
+    pub reach: Vector2D<Number>,
+}
+
+impl Default for ScrollSpring {
+    /// Eases the camera back at about the speed it follows the player, on the GBA's 240x160 screen
+    fn default() -> Self {
+        Self {
+            stiffness: Number::new(1) / 50,
+            damping: Number::new(1) / 4,
+            reach: (120, 80).into(),
+        }
+    }
+}
+
+impl ScrollStop {
+    /// Where the camera should move to this frame when it wants to go to `destination`.
+    ///
+    /// Cameras inside the limits are stopped at them. Cameras past a limit are pulled back to it by
+    /// the spring, with `velocity` keeping track of how fast they are being pulled.
+    pub fn apply(
+        &self,
+        position: Vector2D<Number>,
+        destination: Vector2D<Number>,
+        velocity: &mut Vector2D<Number>,
+        spring: &ScrollSpring,
+    ) -> Vector2D<Number> {
+        (
+            apply_axis(
+                position.x,
+                destination.x,
+                &mut velocity.x,
+                (self.minimum_x, self.maximum_x),
+                spring,
+                spring.reach.x,
+            ),
+            apply_axis(
+                position.y,
+                destination.y,
+                &mut velocity.y,
+                (self.minimum_y, self.maximum_y),
+                spring,
+                spring.reach.y,
+            ),
+        )
+            .into()
+    }
+}
+
+fn apply_axis(
+    position: Number,
+    destination: Number,
+    velocity: &mut Number,
+    (minimum, maximum): (Option<Number>, Option<Number>),
+    spring: &ScrollSpring,
+    reach: Number,
+) -> Number {
+    let outside_minimum = minimum.filter(|&minimum| position < minimum);
+    let outside_maximum = maximum.filter(|&maximum| position > maximum);
+
+    if let Some(minimum) = outside_minimum.filter(|&minimum| minimum - position <= reach) {
+        return spring_to_minimum(destination, velocity, minimum, spring);
+    }
+    if let Some(maximum) = outside_maximum.filter(|&maximum| position - maximum <= reach) {
+        // a maximum is a minimum when everything is flipped
+        let mut flipped_velocity = -*velocity;
+        let destination = -spring_to_minimum(-destination, &mut flipped_velocity, -maximum, spring);
+        *velocity = -flipped_velocity;
+
+        return destination;
+    }
+
+    *velocity = 0.into();
+
+    let mut destination = destination;
+    if let Some(minimum) = minimum.filter(|_| outside_minimum.is_none()) {
+        destination = destination.max(minimum);
+    }
+    if let Some(maximum) = maximum.filter(|_| outside_maximum.is_none()) {
+        destination = destination.min(maximum);
+    }
+
+    destination
+}
+
+fn spring_to_minimum(
+    destination: Number,
+    velocity: &mut Number,
+    minimum: Number,
+    spring: &ScrollSpring,
+) -> Number {
+    if destination >= minimum {
+        // already heading back inside by itself
+        *velocity = 0.into();
+        return destination;
+    }
+
+    *velocity += (minimum - destination) * spring.stiffness;
+    *velocity -= *velocity * spring.damping;
+
+    let destination = destination + *velocity;
+    if destination >= minimum {
+        // stop at the limit rather than overshooting it and bouncing back
+        *velocity = 0.into();
+        minimum
+    } else {
+        destination
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spring() -> ScrollSpring {
+        ScrollSpring::default()
+    }
+
+    fn minimum_x(minimum: i32) -> ScrollStop {
+        ScrollStop {
+            minimum_x: Some(minimum.into()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn camera_inside_the_limit_stops_at_it() {
+        let stop = minimum_x(100);
+        let mut velocity = (0, 0).into();
+
+        let destination = stop.apply((102, 0).into(), (97, 5).into(), &mut velocity, &spring());
+
+        assert_eq!(destination, (100, 5).into());
+    }
+
+    #[test]
+    fn camera_past_the_limit_eases_back_without_overshooting() {
+        let stop = minimum_x(100);
+        let mut velocity = (0, 0).into();
+        let mut position: Vector2D<Number> = (60, 0).into();
+        let mut largest_step = Number::new(0);
+
+        for _ in 0..120 {
+            let next = stop.apply(position, position, &mut velocity, &spring());
+            assert!(
+                next.x >= position.x,
+                "Camera should only move towards the limit"
+            );
+            assert!(next.x <= 100.into(), "Camera shouldn't overshoot the limit");
+
+            largest_step = largest_step.max(next.x - position.x);
+            position = next;
+        }
+
+        assert_eq!(position.x, 100.into());
+        assert!(
+            largest_step < 10.into(),
+            "Camera shouldn't jump to the limit, moved {largest_step} in a frame"
+        );
+    }
+
+    #[test]
+    fn camera_on_the_other_side_of_the_stop_is_left_alone() {
+        let stop = minimum_x(100);
+        let mut velocity = (0, 0).into();
+
+        let destination = stop.apply((-50, 0).into(), (-53, 0).into(), &mut velocity, &spring());
+
+        assert_eq!(destination, (-53, 0).into());
+    }
+
+    #[test]
+    fn maximum_eases_back_the_other_way() {
+        let stop = ScrollStop {
+            maximum_y: Some(200.into()),
+            ..Default::default()
+        };
+        let mut velocity = (0, 0).into();
+        let mut position: Vector2D<Number> = (0, 250).into();
+
+        for _ in 0..120 {
+            position = stop.apply(position, position, &mut velocity, &spring());
+        }
+
+        assert_eq!(position, (0, 200).into());
+    }
+}
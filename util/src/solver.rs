@@ -0,0 +1,242 @@
+use agb_fixnum::{num, Vector2D};
+
+use crate::{Circle, Collider, Number};
+
+/// The most contacts which are tracked in a single frame. Any more are still resolved, but
+/// aren't reported back.
+pub const MAX_CONTACTS: usize = 8;
+
+/// How many times the solver will try to push the circle out of the colliders it overlaps
+pub const SOLVER_ITERATIONS: usize = 6;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Contact {
+    /// Index of the collider in the iterator given to the solver
+    pub collider_index: usize,
+    pub normal: Vector2D<Number>,
+    /// The deepest the circle overlapped this collider while resolving
+    pub depth: Number,
+    /// Whether the collider was moving into the circle
+    pub is_pushing: bool,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ContactManifold {
+    contacts: [Option<Contact>; MAX_CONTACTS],
+}
+
+impl ContactManifold {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Contact> {
+        self.contacts.iter().flatten()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.iter().next().is_none()
+    }
+
+    fn add(&mut self, contact: Contact) {
+        if let Some(existing) = self
+            .contacts
+            .iter_mut()
+            .flatten()
+            .find(|x| x.collider_index == contact.collider_index)
+        {
+            existing.normal = contact.normal;
+            existing.depth = existing.depth.max(contact.depth);
+            existing.is_pushing |= contact.is_pushing;
+        } else if let Some(empty) = self.contacts.iter_mut().find(|x| x.is_none()) {
+            *empty = Some(contact);
+        }
+    }
+
+    /// The circle is crushed if a moving surface pushes it into a surface facing the other way
+    /// and it overlaps both by more than a few pixels
+    pub fn is_crushed(&self) -> bool {
+        self.iter().filter(|x| x.is_pushing).any(|pusher| {
+            self.iter().any(|other| {
+                // 0.7 is approximately sqrt(2) / 2, so the surfaces are within 45 degrees of opposing
+                pusher.normal.dot(other.normal) < num!(-0.7)
+                    && pusher.depth + other.depth > 3.into()
+            })
+        })
+    }
+}
+
+pub struct Resolution {
+    pub position: Vector2D<Number>,
+    pub manifold: ContactManifold,
+}
+
+/// Pushes the circle out of every collision collider it overlaps.
+///
+/// Each iteration resolves the deepest remaining overlap, so the result doesn't depend on the
+/// order of the colliders, and overlaps which are fixed by pushing out of another collider
+/// (like two segments of the same floor) don't push the circle twice. Overlaps which are equally
+/// deep are picked between by their normals rather than by which came first.
+pub fn resolve_collisions<'a>(
+    colliders: impl Iterator<Item = &'a Collider> + Clone,
+    circle: Circle,
+) -> Resolution {
+    let mut position = circle.position;
+    let mut manifold = ContactManifold::new();
+
+    for _ in 0..SOLVER_ITERATIONS {
+        let circle = Circle {
+            position,
+            radius: circle.radius,
+        };
+
+        let mut deepest: Option<Contact> = None;
+
+        for (collider_index, collider) in colliders.clone().enumerate() {
+            if !collider.tag.is_collision() || !collider.collides_circle(&circle) {
+                continue;
+            }
+
+            let normal = collider.normal_circle(&circle);
+            let depth = collider.overshoot(&circle).dot(normal);
+
+            let contact = Contact {
+                collider_index,
+                normal,
+                depth,
+                is_pushing: collider.velocity.dot(normal) > 0.into(),
+            };
+
+            manifold.add(contact);
+
+            if deepest.map_or(true, |x| is_deeper(&contact, &x)) {
+                deepest = Some(contact);
+            }
+        }
+
+        match deepest {
+            // anything less than this is close enough to just be touching
+            Some(contact) if contact.depth > Number::from_raw(4) => {
+                position += contact.normal * contact.depth;
+            }
+            _ => break,
+        }
+    }
+
+    Resolution { position, manifold }
+}
+
+/// Orders contacts by depth, then by normal so that ties don't depend on the order of the colliders
+fn is_deeper(contact: &Contact, other: &Contact) -> bool {
+    let key = |x: &Contact| (x.depth, x.normal.x, x.normal.y);
+    key(contact) > key(other)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ColliderKind, ColliderTag, Line};
+
+    fn line(start: (i32, i32), end: (i32, i32)) -> Collider {
+        let start: Vector2D<Number> = start.into();
+        let end: Vector2D<Number> = end.into();
+        let direction = (end - start).normalise();
+
+        Collider {
+            kind: ColliderKind::Line(Line {
+                start,
+                end,
+                normal: (direction.y, -direction.x).into(),
+                length: (end - start).magnitude(),
+            }),
+            tag: ColliderTag::CollisionOnly,
+            velocity: (0, 0).into(),
+        }
+    }
+
+    fn player_at(x: Number, y: Number) -> Circle {
+        Circle {
+            position: (x, y).into(),
+            radius: 8.into(),
+        }
+    }
+
+    fn is_resolved(colliders: &[Collider], position: Vector2D<Number>) -> bool {
+        let circle = Circle {
+            position,
+            radius: 8.into(),
+        };
+
+        colliders.iter().all(|collider| {
+            !collider.collides_circle(&circle)
+                || collider
+                    .overshoot(&circle)
+                    .dot(collider.normal_circle(&circle))
+                    <= Number::from_raw(4)
+        })
+    }
+
+    #[test]
+    fn floor_made_of_two_segments_only_pushes_once() {
+        // floor along y = 0 with the normal pointing up
+        let colliders = [line((0, 0), (10, 0)), line((10, 0), (20, 0))];
+
+        let resolution =
+            resolve_collisions(colliders.iter(), player_at(10.into(), Number::new(-6)));
+
+        assert_eq!(resolution.position, (10, -8).into());
+        assert_eq!(resolution.manifold.iter().count(), 2);
+    }
+
+    #[test]
+    fn concave_corner_converges() {
+        // a floor along y = 0 and a wall along x = 0, meeting in a corner
+        let colliders = [line((0, 0), (32, 0)), line((0, -32), (0, 0))];
+
+        let resolution =
+            resolve_collisions(colliders.iter(), player_at(Number::new(5), Number::new(-3)));
+
+        assert!(is_resolved(&colliders, resolution.position));
+        assert!(!resolution.manifold.is_crushed());
+    }
+
+    #[test]
+    fn resolution_is_independent_of_collider_order() {
+        let colliders = [line((0, 0), (32, 0)), line((0, -32), (0, 0))];
+        let reversed = [colliders[1].clone(), colliders[0].clone()];
+
+        let circle = player_at(Number::new(6), Number::new(-5));
+
+        assert_eq!(
+            resolve_collisions(colliders.iter(), circle).position,
+            resolve_collisions(reversed.iter(), circle).position
+        );
+    }
+
+    #[test]
+    fn equally_deep_overlaps_resolve_the_same_in_either_order() {
+        // a roof peaking at the origin, with the circle straight below the peak
+        let colliders = [line((-32, 32), (0, 0)), line((0, 0), (32, 32))];
+        let reversed = [colliders[1].clone(), colliders[0].clone()];
+
+        let circle = player_at(0.into(), 2.into());
+
+        assert_eq!(
+            resolve_collisions(colliders.iter(), circle).position,
+            resolve_collisions(reversed.iter(), circle).position
+        );
+    }
+
+    #[test]
+    fn moving_wall_into_static_wall_crushes() {
+        // a static wall facing right at x = 0 and a wall facing left at x = 12 moving left
+        let mut moving_wall = line((12, 0), (12, -32));
+        moving_wall.velocity = (-2, 0).into();
+        let colliders = [line((0, -32), (0, 0)), moving_wall];
+
+        let resolution =
+            resolve_collisions(colliders.iter(), player_at(6.into(), Number::new(-16)));
+
+        assert!(resolution.manifold.is_crushed());
+    }
+}